@@ -0,0 +1,572 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    ext::IdentExt,
+    parse::{Parse, ParseStream, Parser},
+    punctuated::Punctuated,
+    Attribute, Error, Expr, Ident, Lit, LitStr, Meta, Path, Result, Token, Type, Visibility,
+};
+
+// One entry inside `#[builder(...)]`: a bare `key`, `key = value` where the value is a literal or a
+// path, or a nested `key(...)` list. Keywords are accepted as keys so that entries such as `const`
+// parse.
+pub(crate) struct AttrItem {
+    pub key: Ident,
+    pub value: AttrValue,
+}
+
+pub(crate) enum AttrValue {
+    Flag,
+    Lit(Lit),
+    Path(Path),
+    List(TokenStream),
+}
+
+impl Parse for AttrItem {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let key = input.call(Ident::parse_any)?;
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            if input.peek(Lit) {
+                AttrValue::Lit(input.parse()?)
+            } else {
+                AttrValue::Path(input.parse()?)
+            }
+        } else if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            AttrValue::List(content.parse()?)
+        } else {
+            AttrValue::Flag
+        };
+        Ok(AttrItem { key, value })
+    }
+}
+
+// The `#[builder(...)]` attributes among `attrs`, each paired with the tokens it spans so that
+// errors can point at the whole attribute.
+fn builder_attrs(attrs: &[Attribute]) -> impl Iterator<Item = (TokenStream, &Attribute)> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("builder"))
+        .map(|attr| {
+            let path = &attr.path;
+            let tokens = &attr.tokens;
+            (quote!(#path #tokens), attr)
+        })
+}
+
+fn parse_items(attr: &Attribute) -> Result<Punctuated<AttrItem, Token![,]>> {
+    attr.parse_args_with(Punctuated::parse_terminated)
+}
+
+// the entries of a nested `key(...)` list
+fn parse_list(tokens: &TokenStream) -> Result<Punctuated<AttrItem, Token![,]>> {
+    Punctuated::parse_terminated.parse2(tokens.clone())
+}
+
+/// Options given by `#[builder(...)]` on the struct itself, or on an enum and its variants.
+#[derive(Clone, Default)]
+pub(crate) struct StructOpts {
+    /// `#[builder(typestate)]`: track required fields in the builder's type.
+    pub typestate: bool,
+    /// `#[builder(error = Path)]`: error type returned by build(), which must implement
+    /// `From<{Name}BuilderError>`.
+    pub error: Option<Path>,
+    /// `#[builder(default)]` or `#[builder(default = "expr")]`: a value of the struct whose fields
+    /// are used for the fields left unset.
+    pub default: Option<DefaultValue>,
+    /// `#[builder(pattern = "...")]`: how setters and build() take the builder.
+    pub pattern: Option<Pattern>,
+    /// `#[builder(build_fn(validate = "path"))]`: function checking the builder before build()
+    /// constructs the struct.
+    pub validate: Option<Path>,
+    /// `#[builder(name = "...")]`: name of the builder, instead of `{Name}Builder`.
+    pub name: Option<Ident>,
+    /// `#[builder(vis = "...")]`: visibility of the builder, instead of the struct's.
+    pub vis: Option<Visibility>,
+    /// `#[builder(derive(...))]`: traits derived for the builder.
+    pub derive: Vec<Path>,
+    /// `#[builder(struct_attr(...))]`: attributes forwarded onto the builder.
+    pub struct_attrs: Vec<Meta>,
+    /// `#[builder(serde)]`: the builder implements `Deserialize`, with every field optional.
+    pub serde: Option<Ident>,
+    /// `#[builder(const)]`: builder(), the setters and build() are `const fn`. A const fn cannot
+    /// drop values, so fields whose types need dropping, such as String, are reported at the field.
+    pub constness: Option<Ident>,
+    /// `#[builder(no_std)]`: the generated code only uses `::core`, and `::alloc` for validation.
+    pub no_std: bool,
+    /// `#[builder(doc = "...")]`: documentation of the builder, instead of the generated summary.
+    pub doc: Option<LitStr>,
+    /// `#[builder(build_fn(async))]`: build() is an `async fn`, awaiting the `finalize_with`
+    /// functions marked `async`.
+    pub build_async: Option<Ident>,
+    /// `#[builder(getters)]`: the builder can tell what has been set so far.
+    pub getters: Option<Ident>,
+    /// `#[builder(cli)]`: `from_args()` reads the builder from command line flags.
+    pub cli: Option<Ident>,
+    /// `#[builder(env_prefix = "...")]`: `apply_env()` fills unset fields from environment
+    /// variables whose names start with the prefix.
+    pub env_prefix: Option<LitStr>,
+    /// `#[builder(env_separator = "...")]`: what the environment variable of an `each` field is
+    /// split on, instead of `,`.
+    pub env_separator: Option<LitStr>,
+}
+
+impl StructOpts {
+    pub fn pattern(&self) -> Pattern {
+        match self.pattern {
+            Some(pattern) => pattern,
+            None if self.typestate || self.constness.is_some() => Pattern::Owned,
+            None => Pattern::Mutable,
+        }
+    }
+}
+
+/// The receiver of the builder's setters and build().
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Pattern {
+    /// `owned`: setters take and return `Self`, build() consumes the builder.
+    Owned,
+    /// `mutable`: setters take and return `&mut Self`, build() moves the values out of the builder.
+    Mutable,
+    /// `immutable`: setters return an updated copy, build() clones so the builder can be reused.
+    Immutable,
+}
+
+/// What `#[builder(default)]` falls back to.
+#[derive(Clone)]
+pub(crate) enum DefaultValue {
+    /// `default`: the type's `Default` impl.
+    Trait,
+    /// `default = "expr"`: the given expression.
+    Expr(Box<Expr>),
+}
+
+// The keys accepted in each scope, with how they are written for "expected ..." errors
+const STRUCT_KEYS: &[(&str, &str)] = &[
+    ("typestate", "`typestate`"),
+    ("error", "`error = Path`"),
+    ("default", r#"`default` or `default = "..."`"#),
+    (
+        "pattern",
+        r#"`pattern = "owned"`, `"mutable"` or `"immutable"`"#,
+    ),
+    (
+        "build_fn",
+        r#"`build_fn(validate = "...")` or `build_fn(async)`"#,
+    ),
+    ("name", r#"`name = "..."`"#),
+    ("vis", r#"`vis = "..."`"#),
+    ("derive", "`derive(...)`"),
+    ("struct_attr", "`struct_attr(...)`"),
+    ("serde", "`serde`"),
+    ("const", "`const`"),
+    ("no_std", "`no_std`"),
+    ("doc", r#"`doc = "..."`"#),
+    ("getters", "`getters`"),
+    ("cli", "`cli`"),
+    ("env_prefix", r#"`env_prefix = "..."`"#),
+    ("env_separator", r#"`env_separator = "..."`"#),
+];
+const BUILD_FN_KEYS: &[(&str, &str)] =
+    &[("validate", r#"`validate = "..."`"#), ("async", "`async`")];
+const FIELD_KEYS: &[(&str, &str)] = &[
+    (
+        "each",
+        r#"`each = "..."` or `each(name = "...", ty = "...")`"#,
+    ),
+    ("default", r#"`default` or `default = "..."`"#),
+    (
+        "setter",
+        "`setter(into)`, `setter(strip_option)` or `setter(option)`",
+    ),
+    ("try_setter", "`try_setter`"),
+    ("name", r#"`name = "..."`"#),
+    ("optional", "`optional`"),
+    ("required", "`required`"),
+    ("skip", "`skip`"),
+    ("doc", r#"`doc = "..."`"#),
+    (
+        "finalize_with",
+        r#"`finalize_with = "..."` or `finalize_with(path = "...", ty = "...")`"#,
+    ),
+    ("sub_builder", r#"`sub_builder` or `sub_builder = "..."`"#),
+];
+const SETTER_KEYS: &[(&str, &str)] = &[
+    ("into", "`into`"),
+    ("strip_option", "`strip_option`"),
+    ("option", "`option`"),
+];
+const EACH_KEYS: &[(&str, &str)] = &[("name", r#"`name = "..."`"#), ("ty", r#"`ty = "..."`"#)];
+const FINALIZE_KEYS: &[(&str, &str)] = &[
+    ("path", r#"`path = "..."`"#),
+    ("ty", r#"`ty = "..."`"#),
+    ("async", "`async`"),
+];
+
+// The keys given so far in one scope, to reject the ones given twice
+#[derive(Default)]
+struct Seen(Vec<String>);
+
+impl Seen {
+    fn insert(&mut self, key: &Ident) -> Result<()> {
+        let name = key.to_string();
+        if self.0.contains(&name) {
+            return Err(Error::new_spanned(key, format!("duplicate `{}`", name)));
+        }
+        self.0.push(name);
+        Ok(())
+    }
+}
+
+// The error for an item that none of the arms accepted: either a known key written in the wrong
+// form, or an unknown key, for which the closest known one is suggested if it looks like a typo.
+fn invalid_item(item: &AttrItem, scope: &str, keys: &[(&str, &str)]) -> Error {
+    let key = &item.key;
+    if let Some((_, usage)) = keys.iter().find(|(name, _)| key == name) {
+        return Error::new_spanned(key, format!("expected {}", usage));
+    }
+    let mut message = format!("unrecognized {} `{}`", scope, key);
+    let name = key.to_string();
+    let closest = keys
+        .iter()
+        .map(|(known, _)| (edit_distance(&name, known), known))
+        .filter(|(distance, known)| *distance <= (known.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance);
+    if let Some((_, known)) = closest {
+        message += &format!(", did you mean `{}`?", known);
+    }
+    Error::new_spanned(key, message)
+}
+
+// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// `inherited` holds the options of the enum when parsing the attributes of one of its variants
+pub(crate) fn parse_struct_opts(attrs: &[Attribute], inherited: &StructOpts) -> Result<StructOpts> {
+    let mut opts = inherited.clone();
+    let mut pattern_lit = None;
+    let mut seen = Seen::default();
+    for (_, attr) in builder_attrs(attrs) {
+        for item in parse_items(attr)? {
+            seen.insert(&item.key)?;
+            match (item.key.to_string().as_str(), &item.value) {
+                ("typestate", AttrValue::Flag) => opts.typestate = true,
+                ("error", AttrValue::Path(path)) => opts.error = Some(path.clone()),
+                ("error", AttrValue::Lit(Lit::Str(s))) => opts.error = Some(s.parse()?),
+                ("default", AttrValue::Flag) => opts.default = Some(DefaultValue::Trait),
+                ("default", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.default = Some(DefaultValue::Expr(Box::new(s.parse()?)))
+                }
+                ("pattern", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.pattern = Some(match s.value().as_str() {
+                        "owned" => Pattern::Owned,
+                        "mutable" => Pattern::Mutable,
+                        "immutable" => Pattern::Immutable,
+                        _ => {
+                            return Err(Error::new_spanned(
+                                s,
+                                r#"expected `"owned"`, `"mutable"` or `"immutable"`"#,
+                            ))
+                        }
+                    });
+                    pattern_lit = Some(s.clone());
+                }
+                ("build_fn", AttrValue::List(list)) => {
+                    let mut seen = Seen::default();
+                    for item in parse_list(list)? {
+                        seen.insert(&item.key)?;
+                        match (item.key.to_string().as_str(), &item.value) {
+                            ("validate", AttrValue::Path(path)) => {
+                                opts.validate = Some(path.clone())
+                            }
+                            ("validate", AttrValue::Lit(Lit::Str(s))) => {
+                                opts.validate = Some(s.parse()?)
+                            }
+                            ("async", AttrValue::Flag) => opts.build_async = Some(item.key),
+                            _ => {
+                                return Err(invalid_item(&item, "`build_fn` option", BUILD_FN_KEYS))
+                            }
+                        }
+                    }
+                }
+                ("name", AttrValue::Lit(Lit::Str(s))) => opts.name = Some(s.parse()?),
+                ("vis", AttrValue::Lit(Lit::Str(s))) => opts.vis = Some(s.parse()?),
+                ("derive", AttrValue::List(list)) => {
+                    opts.derive = Punctuated::<Path, Token![,]>::parse_terminated
+                        .parse2(list.clone())?
+                        .into_iter()
+                        .collect()
+                }
+                ("struct_attr", AttrValue::List(list)) => {
+                    opts.struct_attrs = Punctuated::<Meta, Token![,]>::parse_terminated
+                        .parse2(list.clone())?
+                        .into_iter()
+                        .collect()
+                }
+                ("serde", AttrValue::Flag) if cfg!(feature = "serde") => {
+                    opts.serde = Some(item.key)
+                }
+                ("serde", AttrValue::Flag) => {
+                    return Err(Error::new_spanned(
+                        item.key,
+                        "`serde` needs the `serde` feature of derive_builder",
+                    ))
+                }
+                ("const", AttrValue::Flag) => opts.constness = Some(item.key),
+                ("no_std", AttrValue::Flag) => opts.no_std = true,
+                ("doc", AttrValue::Lit(Lit::Str(s))) => opts.doc = Some(s.clone()),
+                ("getters", AttrValue::Flag) => opts.getters = Some(item.key),
+                ("cli", AttrValue::Flag) if cfg!(feature = "cli") => opts.cli = Some(item.key),
+                ("env_prefix", AttrValue::Lit(Lit::Str(s))) => opts.env_prefix = Some(s.clone()),
+                ("env_separator", AttrValue::Lit(Lit::Str(s))) => {
+                    if s.value().is_empty() {
+                        return Err(Error::new_spanned(s, "`env_separator` cannot be empty"));
+                    }
+                    opts.env_separator = Some(s.clone())
+                }
+                ("cli", AttrValue::Flag) => {
+                    return Err(Error::new_spanned(
+                        item.key,
+                        "`cli` needs the `cli` feature of derive_builder",
+                    ))
+                }
+                _ => return Err(invalid_item(&item, "struct attribute", STRUCT_KEYS)),
+            }
+        }
+    }
+    if let (true, Some(lit)) = (opts.typestate, &pattern_lit) {
+        if opts.pattern != Some(Pattern::Owned) {
+            return Err(Error::new_spanned(
+                lit,
+                "typestate builders are always owned: every setter returns a builder of a new type",
+            ));
+        }
+    }
+    if let (Some(_), Some(lit)) = (&opts.constness, &pattern_lit) {
+        if opts.pattern != Some(Pattern::Owned) {
+            return Err(Error::new_spanned(
+                lit,
+                "const builders are always owned: a const fn cannot take the builder by reference and move or clone its fields",
+            ));
+        }
+    }
+    if let (Some(separator), None) = (&opts.env_separator, &opts.env_prefix) {
+        return Err(Error::new_spanned(
+            separator,
+            "`env_separator` has no effect without `env_prefix`",
+        ));
+    }
+    if let Some(key) = &opts.constness {
+        // nothing that calls a trait method can be const
+        let unsupported = match (&opts.validate, &opts.error, &opts.default) {
+            (Some(_), _, _) => Some("`build_fn(validate)`"),
+            (_, Some(_), _) => Some("`error`"),
+            (_, _, Some(DefaultValue::Trait)) => {
+                Some(r#"`default`, use `default = "..."` with a const expression"#)
+            }
+            _ if opts.build_async.is_some() => Some("`build_fn(async)`"),
+            _ => None,
+        };
+        if let Some(unsupported) = unsupported {
+            return Err(Error::new_spanned(
+                key,
+                format!("`const` builders do not support {}", unsupported),
+            ));
+        }
+    }
+    Ok(opts)
+}
+
+/// Options given by `#[builder(...)]` on a field.
+#[derive(Default)]
+pub(crate) struct FieldOpts {
+    /// `#[builder(each = "name")]` or `#[builder(each(name = "...", ty = "..."))]`: the
+    /// one-element-at-a-time setter.
+    pub each: Option<Each>,
+    /// `#[builder(default)]` or `#[builder(default = "expr")]`: value of the field when unset.
+    pub default: Option<DefaultValue>,
+    /// `#[builder(setter(into))]`: the setter accepts anything that converts into the field.
+    pub setter_into: bool,
+    /// `#[builder(setter(option))]` or `#[builder(setter(strip_option))]`: whether the setter of an
+    /// `Option` field takes the `Option` itself rather than its content.
+    pub setter_option: Option<(bool, Ident)>,
+    /// `#[builder(try_setter)]`: also generate a `try_` setter going through `TryInto`.
+    pub try_setter: bool,
+    /// `#[builder(optional)]` or `#[builder(required)]`: whether the field is an `Option`, for
+    /// types the field type alone does not tell, such as `type Maybe<T> = Option<T>`.
+    pub presence: Option<(bool, Ident)>,
+    /// `#[builder(name = "...")]`: name of the setter, instead of the field name or `_0`, `_1`, ...
+    /// for tuple fields.
+    pub name: Option<Ident>,
+    /// `#[builder(skip)]`: no setter, build() gives the field its default.
+    pub skip: Option<Ident>,
+    /// `#[builder(doc = "...")]`: documentation of the setters, instead of the field's.
+    pub doc: Option<LitStr>,
+    /// `#[builder(finalize_with = "path")]` or
+    /// `#[builder(finalize_with(path = "...", ty = "...", async))]`:
+    /// function build() passes the stored value through to get the value of the field. A mutable
+    /// build() passes a clone, so the stored type must then be Clone.
+    pub finalize_with: Option<Finalize>,
+    /// `#[builder(sub_builder)]` or `#[builder(sub_builder = "...")]`: the field is set through
+    /// the builder of its type, optionally named when it is not `{Type}Builder`. A mutable build()
+    /// builds from a clone of that builder, which must then be Clone.
+    pub sub_builder: Option<(Ident, Option<Type>)>,
+}
+
+/// The setter adding one element to a collection field.
+pub(crate) struct Each {
+    /// the `each` key itself, for errors
+    pub key: Ident,
+    pub name: Ident,
+    /// `ty = "..."`: the element type, for collections whose element cannot be inferred.
+    pub ty: Option<Type>,
+}
+
+/// The conversion of a field's stored value in build().
+pub(crate) struct Finalize {
+    /// the `finalize_with` key itself, for errors
+    pub key: Ident,
+    pub path: Path,
+    /// `ty = "..."`: the type the setters take and the builder stores, when it is not the field's.
+    pub ty: Option<Type>,
+    /// `async`: the function is an `async fn`, awaited by a `build_fn(async)` build().
+    pub asyncness: Option<Ident>,
+}
+
+// if user provides something like #[builder(eac = "arg")], then we should report an error instead
+// of ignoring it
+pub(crate) fn parse_field_opts(attrs: &[Attribute]) -> Result<FieldOpts> {
+    let mut opts = FieldOpts::default();
+    let mut seen = Seen::default();
+    for (tokens, attr) in builder_attrs(attrs) {
+        for item in parse_items(attr)? {
+            seen.insert(&item.key)?;
+            match (item.key.to_string().as_str(), &item.value) {
+                ("each", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.each = Some(Each {
+                        key: item.key,
+                        name: s.parse()?,
+                        ty: None,
+                    })
+                }
+                ("each", AttrValue::List(list)) => {
+                    let (mut name, mut ty) = (None, None);
+                    let mut seen = Seen::default();
+                    for item in parse_list(list)? {
+                        seen.insert(&item.key)?;
+                        match (item.key.to_string().as_str(), &item.value) {
+                            ("name", AttrValue::Lit(Lit::Str(s))) => name = Some(s.parse()?),
+                            ("ty", AttrValue::Lit(Lit::Str(s))) => ty = Some(s.parse()?),
+                            _ => return Err(invalid_item(&item, "`each` option", EACH_KEYS)),
+                        }
+                    }
+                    let name = name.ok_or_else(|| {
+                        Error::new_spanned(&tokens, r#"missing `name = "..."` in `each(...)`"#)
+                    })?;
+                    opts.each = Some(Each {
+                        key: item.key,
+                        name,
+                        ty,
+                    });
+                }
+                ("default", AttrValue::Flag) => opts.default = Some(DefaultValue::Trait),
+                ("default", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.default = Some(DefaultValue::Expr(Box::new(s.parse()?)))
+                }
+                ("setter", AttrValue::List(list)) => {
+                    let mut seen = Seen::default();
+                    for item in parse_list(list)? {
+                        seen.insert(&item.key)?;
+                        let option = match (item.key.to_string().as_str(), &item.value) {
+                            ("into", AttrValue::Flag) => {
+                                opts.setter_into = true;
+                                continue;
+                            }
+                            ("option", AttrValue::Flag) => true,
+                            ("strip_option", AttrValue::Flag) => false,
+                            _ => return Err(invalid_item(&item, "`setter` option", SETTER_KEYS)),
+                        };
+                        if let Some((_, other)) = &opts.setter_option {
+                            return Err(conflict(&item.key, other));
+                        }
+                        opts.setter_option = Some((option, item.key));
+                    }
+                }
+                ("try_setter", AttrValue::Flag) => opts.try_setter = true,
+                ("optional" | "required", AttrValue::Flag) => {
+                    if let Some((_, other)) = &opts.presence {
+                        return Err(conflict(&item.key, other));
+                    }
+                    opts.presence = Some((item.key == "optional", item.key));
+                }
+                ("name", AttrValue::Lit(Lit::Str(s))) => opts.name = Some(s.parse()?),
+                ("skip", AttrValue::Flag) => opts.skip = Some(item.key),
+                ("doc", AttrValue::Lit(Lit::Str(s))) => opts.doc = Some(s.clone()),
+                ("finalize_with", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.finalize_with = Some(Finalize {
+                        key: item.key,
+                        path: s.parse()?,
+                        ty: None,
+                        asyncness: None,
+                    })
+                }
+                ("finalize_with", AttrValue::List(list)) => {
+                    let (mut path, mut ty, mut asyncness) = (None, None, None);
+                    let mut seen = Seen::default();
+                    for item in parse_list(list)? {
+                        seen.insert(&item.key)?;
+                        match (item.key.to_string().as_str(), &item.value) {
+                            ("path", AttrValue::Lit(Lit::Str(s))) => path = Some(s.parse()?),
+                            ("ty", AttrValue::Lit(Lit::Str(s))) => ty = Some(s.parse()?),
+                            ("async", AttrValue::Flag) => asyncness = Some(item.key),
+                            _ => {
+                                return Err(invalid_item(
+                                    &item,
+                                    "`finalize_with` option",
+                                    FINALIZE_KEYS,
+                                ))
+                            }
+                        }
+                    }
+                    let path = path.ok_or_else(|| {
+                        Error::new_spanned(
+                            &tokens,
+                            r#"missing `path = "..."` in `finalize_with(...)`"#,
+                        )
+                    })?;
+                    opts.finalize_with = Some(Finalize {
+                        key: item.key,
+                        path,
+                        ty,
+                        asyncness,
+                    });
+                }
+                ("sub_builder", AttrValue::Flag) => opts.sub_builder = Some((item.key, None)),
+                ("sub_builder", AttrValue::Lit(Lit::Str(s))) => {
+                    opts.sub_builder = Some((item.key, Some(s.parse()?)))
+                }
+                _ => return Err(invalid_item(&item, "field attribute", FIELD_KEYS)),
+            }
+        }
+    }
+    Ok(opts)
+}
+
+fn conflict(key: &Ident, other: &Ident) -> Error {
+    Error::new_spanned(key, format!("`{}` conflicts with `{}`", key, other))
+}
@@ -0,0 +1,328 @@
+// `#[builder(cli)]`: `from_args()` fills a builder from command line arguments, so that a binary
+// needs no second struct describing its flags. Every field is a `--kebab-case` flag taking its
+// value as the next argument or after `=`, parsed with FromStr:
+//
+//   - required fields are required flags, reported by from_args() when missing
+//   - fields with a default or of type Option are optional flags
+//   - `each` fields are repeated flags named after their setter, a map entry given as `KEY=VALUE`
+//   - `bool` fields are switches, true when given and false otherwise, or given as `--flag=false`
+//
+// `-h` and `--help` return the help text, made from the doc comments of the struct and its fields,
+// as an error for the caller to print. The parser is generated inline with the builder, and needs
+// nothing but std.
+
+use crate::{parse_each_item, parse_text, BuilderField, BuilderInput, EachItem, FieldType};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Attribute, Error, Ident, Lit, Meta, Result, Type};
+
+pub(crate) fn error_name(input: &BuilderInput) -> Ident {
+    format_ident!("{}CliError", input.builder_name)
+}
+
+// What a flag takes, and how its parsed value is stored in the builder
+enum FlagValue<'a> {
+    Switch,
+    // a value of the type, stored as is or inside the Option of an Option field
+    Value(&'a Type, bool),
+    Each(&'a EachItem),
+}
+
+struct Flag<'a> {
+    field: &'a BuilderField<'a>,
+    // `--current-dir`
+    flag: String,
+    // `CURRENT_DIR`, shown in the help
+    value_name: String,
+    value: FlagValue<'a>,
+}
+
+pub(crate) fn expand(input: &BuilderInput) -> Result<TokenStream> {
+    let flags = input.fields.iter().map(flag).collect::<Result<Vec<_>>>()?;
+    let help = help(input, &flags);
+    let error_name = error_name(input);
+
+    let arms = flags.iter().map(|flag| parse_arm(&error_name, flag));
+    let missing = flags
+        .iter()
+        .filter(|flag| flag.field.is_required())
+        .map(|flag| {
+            let (name, flag_str) = (&flag.field.name, &flag.flag);
+            match flag.value {
+                // a switch that is not given is false
+                FlagValue::Switch => quote! {
+                    builder.#name.get_or_insert(false);
+                },
+                _ => quote! {
+                    if ::core::option::Option::is_none(&builder.#name) {
+                        return ::core::result::Result::Err(#error_name::MissingFlag(#flag_str));
+                    }
+                },
+            }
+        });
+
+    let name = input.name;
+    let builder_name = &input.builder_name;
+    let builder_fn = input.builder_fn();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let vis = &input.vis;
+    let from_args_doc = format!(
+        "Reads the fields of a [`{}`] from command line arguments, without the program name, \
+         such as `std::env::args().skip(1)`.",
+        builder_name
+    );
+    let error_doc = format!("Error returned by [`{}::from_args`].", builder_name);
+    Ok(quote! {
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            /// The text printed for `--help`, listing the flags read by from_args().
+            #vis fn cli_help() -> &'static str {
+                #help
+            }
+
+            #[doc = #from_args_doc]
+            #vis fn from_args<__I>(args: __I) -> ::core::result::Result<Self, #error_name>
+            where
+                __I: ::core::iter::IntoIterator,
+                __I::Item: ::core::convert::Into<::std::string::String>,
+            {
+                let mut builder = <#name #ty_generics>::#builder_fn();
+                let mut args = ::core::iter::Iterator::map(
+                    ::core::iter::IntoIterator::into_iter(args),
+                    ::core::convert::Into::<::std::string::String>::into,
+                );
+                while let ::core::option::Option::Some(arg) = ::core::iter::Iterator::next(&mut args) {
+                    // `--flag=value` gives the value inline, otherwise it is the next argument
+                    let (flag, inline) = match arg.find('=') {
+                        ::core::option::Option::Some(at) if arg.starts_with("--") => (
+                            ::std::string::ToString::to_string(&arg[..at]),
+                            ::core::option::Option::Some(::std::string::ToString::to_string(&arg[at + 1..])),
+                        ),
+                        _ => (arg, ::core::option::Option::None),
+                    };
+                    match flag.as_str() {
+                        "-h" | "--help" if inline.is_none() => {
+                            return ::core::result::Result::Err(#error_name::Help(#help));
+                        }
+                        #(#arms)*
+                        _ => return ::core::result::Result::Err(#error_name::UnknownArgument(flag)),
+                    }
+                }
+                #(#missing)*
+                ::core::result::Result::Ok(builder)
+            }
+        }
+
+        #[doc = #error_doc]
+        #[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq, ::core::cmp::Eq)]
+        #[non_exhaustive]
+        #vis enum #error_name {
+            /// `-h` or `--help` was given, with the help text to print.
+            Help(&'static str),
+            /// An argument that is none of the flags.
+            UnknownArgument(::std::string::String),
+            /// A flag was given as the last argument, without its value.
+            MissingValue(&'static str),
+            /// The value of a flag could not be parsed.
+            InvalidValue {
+                /// The flag, such as `--port`.
+                flag: &'static str,
+                /// The value given to the flag.
+                value: ::std::string::String,
+                /// Why the value could not be parsed.
+                message: ::std::string::String,
+            },
+            /// A required flag was not given.
+            MissingFlag(&'static str),
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                match self {
+                    Self::Help(help) => f.write_str(help),
+                    Self::UnknownArgument(arg) => {
+                        ::core::write!(f, "unexpected argument `{}`, see --help", arg)
+                    }
+                    Self::MissingValue(flag) => ::core::write!(f, "`{}` needs a value", flag),
+                    Self::InvalidValue { flag, value, message } => {
+                        ::core::write!(f, "invalid value `{}` for `{}`: {}", value, flag, message)
+                    }
+                    Self::MissingFlag(flag) => ::core::write!(f, "missing required flag `{}`", flag),
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error_name {}
+    })
+}
+
+fn flag<'a>(field: &'a BuilderField<'a>) -> Result<Flag<'a>> {
+    let (name, value) = match &field.kind {
+        FieldType::RawType if is_bool(&field.ty) => (&field.name, FlagValue::Switch),
+        FieldType::RawType => (&field.name, FlagValue::Value(&field.ty, false)),
+        FieldType::OptionType(inner) => (&field.name, FlagValue::Value(inner, true)),
+        FieldType::EachType(each, item) => (each, FlagValue::Each(item)),
+        FieldType::SubBuilder => {
+            let (key, _) = field.opts.sub_builder.as_ref().unwrap();
+            return Err(Error::new_spanned(
+                key,
+                "`sub_builder` fields are not supported with `cli`",
+            ));
+        }
+    };
+    // `_dry_run` is `--dry-run`, and the unnamed fields of a tuple struct are `--0`, `--1`...
+    let name = name.unraw().to_string();
+    let name = name.trim_start_matches('_');
+    let value_name = match value {
+        FlagValue::Each(EachItem::Entry(..)) => "KEY=VALUE".to_owned(),
+        _ if name.bytes().all(|b| b.is_ascii_digit()) => "VALUE".to_owned(),
+        _ => name.to_uppercase(),
+    };
+    Ok(Flag {
+        field,
+        flag: format!("--{}", name.replace('_', "-")),
+        value_name,
+        value,
+    })
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("bool"))
+}
+
+// the arm of the match on the flag reading one flag into the builder
+fn parse_arm(error_name: &Ident, flag: &Flag) -> TokenStream {
+    let (name, flag_str) = (&flag.field.name, &flag.flag);
+    let invalid = |value: TokenStream, message: TokenStream| {
+        quote! {
+            #error_name::InvalidValue {
+                flag: #flag_str,
+                value: #value,
+                message: #message,
+            }
+        }
+    };
+    let next_value = quote! {
+        let value = match inline {
+            ::core::option::Option::Some(value) => value,
+            ::core::option::Option::None => match ::core::iter::Iterator::next(&mut args) {
+                ::core::option::Option::Some(value) => value,
+                ::core::option::Option::None => {
+                    return ::core::result::Result::Err(#error_name::MissingValue(#flag_str));
+                }
+            },
+        };
+    };
+    let store = match &flag.value {
+        FlagValue::Switch => {
+            let parse = parse_text("value", &quote!(bool), &invalid);
+            return quote! {
+                #flag_str => {
+                    let value = match inline {
+                        ::core::option::Option::Some(value) => {
+                            #parse
+                            value
+                        }
+                        ::core::option::Option::None => true,
+                    };
+                    builder.#name = ::core::option::Option::Some(value);
+                }
+            };
+        }
+        FlagValue::Value(ty, option) => {
+            let parse = parse_text("value", &quote!(#ty), &invalid);
+            let value = match option {
+                true => quote!(::core::option::Option::Some(value)),
+                false => quote!(value),
+            };
+            quote! {
+                #parse
+                builder.#name = ::core::option::Option::Some(#value);
+            }
+        }
+        FlagValue::Each(item) => {
+            let parse = parse_each_item(item, &invalid);
+            quote! {
+                #parse
+                ::core::iter::Extend::extend(
+                    builder.#name.get_or_insert_with(::core::default::Default::default),
+                    ::core::iter::once(value),
+                );
+            }
+        }
+    };
+    quote! {
+        #flag_str => {
+            #next_value
+            #store
+        }
+    }
+}
+
+// the first paragraph of the struct's docs, then one line per flag with the first paragraph of
+// the field's docs
+fn help(input: &BuilderInput, flags: &[Flag]) -> String {
+    let mut lines: Vec<(String, String)> = flags
+        .iter()
+        .map(|flag| {
+            let usage = match flag.value {
+                FlagValue::Switch => flag.flag.clone(),
+                FlagValue::Each(_) => format!("{} <{}>...", flag.flag, flag.value_name),
+                FlagValue::Value(..) => format!("{} <{}>", flag.flag, flag.value_name),
+            };
+            let mut doc = match &flag.field.opts.doc {
+                Some(doc) => first_paragraph(&[doc.value()]),
+                None => doc_text(&flag.field.docs),
+            };
+            if flag.field.is_required() && !matches!(flag.value, FlagValue::Switch) {
+                doc = match doc.is_empty() {
+                    true => "(required)".to_owned(),
+                    false => format!("{} (required)", doc),
+                };
+            }
+            (usage, doc)
+        })
+        .collect();
+    lines.push(("-h, --help".to_owned(), "Print this help".to_owned()));
+    let width = lines
+        .iter()
+        .map(|(usage, _)| usage.len())
+        .max()
+        .unwrap_or(0);
+    let mut help = doc_text(&input.docs);
+    if !help.is_empty() {
+        help += "\n\n";
+    }
+    help += "Options:";
+    for (usage, doc) in lines {
+        let line = format!("  {:width$}  {}", usage, doc, width = width);
+        help += "\n";
+        help += line.trim_end();
+    }
+    help
+}
+
+// the first paragraph of `///` comments, on one line
+fn doc_text(docs: &[&Attribute]) -> String {
+    let lines: Vec<String> = docs
+        .iter()
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(meta)) => match meta.lit {
+                Lit::Str(doc) => Some(doc.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    first_paragraph(&lines)
+}
+
+fn first_paragraph(lines: &[String]) -> String {
+    let words: Vec<&str> = lines
+        .iter()
+        .flat_map(|line| line.lines())
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect();
+    words.join(" ")
+}
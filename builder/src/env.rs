@@ -0,0 +1,167 @@
+// `#[builder(env_prefix = "APP_")]`: `apply_env()` fills every field that is still unset from the
+// environment variable named after it, `APP_CURRENT_DIR` for `current_dir`, parsed with FromStr.
+// A field renamed with `#[builder(name = "...")]` keeps the variable of its own name, and the
+// fields of a tuple struct are read from `APP_0`, `APP_1` and so on.
+// The value of an `each` field is a list split on `env_separator`, `,` by default, whose map
+// entries are written `KEY=VALUE`. `apply_env_from()` does the same from any list of variables,
+// such as a `.env` file or a test's fixed environment.
+//
+// Fields set before are kept, so that the environment only fills the gaps left by the setters, or
+// overrides a configuration file when applied before the file's builder is merged in.
+
+use crate::{parse_each_item, parse_text, BuilderInput, FieldType};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Error, Ident, Member, Result};
+
+pub(crate) fn error_name(input: &BuilderInput) -> Ident {
+    format_ident!("{}EnvError", input.builder_name)
+}
+
+pub(crate) fn expand(input: &BuilderInput) -> Result<TokenStream> {
+    let prefix = match &input.opts.env_prefix {
+        Some(prefix) => prefix.value(),
+        None => return Ok(TokenStream::new()),
+    };
+    let separator = match &input.opts.env_separator {
+        Some(separator) => separator.value(),
+        None => ",".to_owned(),
+    };
+    let error_name = error_name(input);
+
+    let mut arms = Vec::with_capacity(input.fields.len());
+    for field in &input.fields {
+        let (name, ty) = (&field.name, &field.ty);
+        // named after the field rather than its setter, like the keys of `serde`
+        let var = match &field.member {
+            Member::Named(member) => {
+                format!("{}{}", prefix, member.unraw().to_string().to_uppercase())
+            }
+            Member::Unnamed(index) => format!("{}{}", prefix, index.index),
+        };
+        let invalid = |value: TokenStream, message: TokenStream| {
+            quote! {
+                #error_name::InvalidValue {
+                    var: #var,
+                    value: #value,
+                    message: #message,
+                }
+            }
+        };
+        let store = match &field.kind {
+            FieldType::RawType => {
+                let parse = parse_text("value", &quote!(#ty), &invalid);
+                quote! {
+                    #parse
+                    self.#name = ::core::option::Option::Some(value);
+                }
+            }
+            FieldType::OptionType(inner) => {
+                let parse = parse_text("value", &quote!(#inner), &invalid);
+                quote! {
+                    #parse
+                    self.#name = ::core::option::Option::Some(::core::option::Option::Some(value));
+                }
+            }
+            FieldType::EachType(_, item) => {
+                let parse = parse_each_item(item, &invalid);
+                quote! {
+                    let mut collection: #ty = ::core::default::Default::default();
+                    for value in value.split(#separator).filter(|item| !item.is_empty()) {
+                        let value = ::std::string::ToString::to_string(value);
+                        #parse
+                        ::core::iter::Extend::extend(&mut collection, ::core::iter::once(value));
+                    }
+                    self.#name = ::core::option::Option::Some(collection);
+                }
+            }
+            FieldType::SubBuilder => {
+                let (key, _) = field.opts.sub_builder.as_ref().unwrap();
+                return Err(Error::new_spanned(
+                    key,
+                    "`sub_builder` fields are not supported with `env_prefix`",
+                ));
+            }
+        };
+        arms.push(quote! {
+            #var if ::core::option::Option::is_none(&self.#name) => {
+                let value = ::std::string::ToString::to_string(value);
+                #store
+            }
+        });
+    }
+
+    let builder_name = &input.builder_name;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let vis = &input.vis;
+    let apply_env_doc = format!(
+        "Sets every field that is not set yet from its environment variable, `{}` followed by \
+         the name of the field in upper case, or its index in a tuple struct.",
+        prefix
+    );
+    let error_doc = format!(
+        "Error returned by [`{}::apply_env`] and [`{0}::apply_env_from`].",
+        builder_name
+    );
+    Ok(quote! {
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            #[doc = #apply_env_doc]
+            #vis fn apply_env(&mut self) -> ::core::result::Result<&mut Self, #error_name> {
+                // variables that are not Unicode cannot be meant for the builder's fields
+                self.apply_env_from(::core::iter::Iterator::filter_map(
+                    ::std::env::vars_os(),
+                    |(key, value)| {
+                        ::core::option::Option::Some((
+                            ::std::ffi::OsString::into_string(key).ok()?,
+                            ::std::ffi::OsString::into_string(value).ok()?,
+                        ))
+                    },
+                ))
+            }
+
+            /// Like apply_env(), taking the variables from `vars` instead of the environment.
+            #vis fn apply_env_from<__I, __K, __V>(&mut self, vars: __I) -> ::core::result::Result<&mut Self, #error_name>
+            where
+                __I: ::core::iter::IntoIterator<Item = (__K, __V)>,
+                __K: ::core::convert::AsRef<str>,
+                __V: ::core::convert::AsRef<str>,
+            {
+                for (key, value) in vars {
+                    let value = value.as_ref();
+                    match key.as_ref() {
+                        #(#arms)*
+                        _ => {}
+                    }
+                }
+                ::core::result::Result::Ok(self)
+            }
+        }
+
+        #[doc = #error_doc]
+        #[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq, ::core::cmp::Eq)]
+        #[non_exhaustive]
+        #vis enum #error_name {
+            /// The value of an environment variable could not be parsed.
+            InvalidValue {
+                /// The variable, such as `APP_PORT`.
+                var: &'static str,
+                /// The value of the variable.
+                value: ::std::string::String,
+                /// Why the value could not be parsed.
+                message: ::std::string::String,
+            },
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                match self {
+                    Self::InvalidValue { var, value, message } => {
+                        ::core::write!(f, "invalid value `{}` for `{}`: {}", value, var, message)
+                    }
+                }
+            }
+        }
+
+        impl ::std::error::Error for #error_name {}
+    })
+}
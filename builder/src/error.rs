@@ -0,0 +1,118 @@
+// The error type returned by build(). Every builder gets its own `{Name}BuilderError` enum so that
+// callers can match on what went wrong; with `#[builder(error = Path)]` build() returns `Path`
+// instead, converting the generated error through `From`.
+
+use crate::BuilderInput;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+pub(crate) fn error_name(input: &BuilderInput) -> Ident {
+    format_ident!("{}Error", input.builder_name)
+}
+
+// the error type in the signature of build()
+pub(crate) fn build_error_type(input: &BuilderInput) -> TokenStream {
+    match &input.opts.error {
+        Some(path) => quote!(#path),
+        None => {
+            let error_name = error_name(input);
+            quote!(#error_name)
+        }
+    }
+}
+
+pub(crate) fn expand(input: &BuilderInput) -> TokenStream {
+    let error_name = error_name(input);
+    let builder_name = &input.builder_name;
+    let vis = &input.vis;
+    let doc = format!("Error returned by [`{}::build`].", builder_name);
+    let alloc = input.alloc_crate();
+    let validation_error = input.has_validation_error().then(|| {
+        quote! {
+            /// The builder's contents were rejected by its validation.
+            ValidationError(#alloc::string::String),
+        }
+    });
+    let validation_message = input
+        .has_validation_error()
+        .then(|| quote!(Self::ValidationError(message) => ::core::write!(f, "{}", message),));
+    let finalize_error = input.has_finalize().then(|| {
+        quote! {
+            /// The `finalize_with` function of a field, named first, failed with the given message.
+            FinalizeError(&'static str, #alloc::string::String),
+        }
+    });
+    let sub_builder_error = input.has_sub_builder().then(|| {
+        quote! {
+            /// A field of a sub-builder was not set, given by its path such as `server.port`.
+            UninitializedNestedField(#alloc::string::String),
+            /// The sub-builder of a field, named first, failed with the given message.
+            SubBuilderError(&'static str, #alloc::string::String),
+        }
+    });
+    let sub_builder_message = input.has_sub_builder().then(|| {
+        quote! {
+            Self::UninitializedNestedField(path) => ::core::write!(f, "`{}` is not set", path),
+            Self::SubBuilderError(field, message) => ::core::write!(f, "`{}` could not be built: {}", field, message),
+        }
+    });
+    let nested_path = input.has_sub_builder().then(|| {
+        quote!(Self::UninitializedNestedField(path) => ::core::option::Option::Some(::core::clone::Clone::clone(path)),)
+    });
+    // what a builder holding this one as a sub-builder needs to report the path of a missing field
+    let uninitialized_field = (!input.opts.no_std).then(|| {
+        quote! {
+            impl #error_name {
+                /// The path of the field that was not set, such as `port` or `server.port`, if that
+                /// is why build() failed.
+                #vis fn uninitialized_field(&self) -> ::core::option::Option<::std::string::String> {
+                    #[allow(unreachable_patterns)]
+                    match self {
+                        Self::UninitializedField(field) => {
+                            ::core::option::Option::Some(::std::string::ToString::to_string(field))
+                        }
+                        #nested_path
+                        _ => ::core::option::Option::None,
+                    }
+                }
+            }
+        }
+    });
+    let finalize_message = input.has_finalize().then(|| {
+        quote! {
+            Self::FinalizeError(field, message) => ::core::write!(f, "`{}` could not be finalized: {}", field, message),
+        }
+    });
+    // `core::error::Error` is too recent for the firmware toolchains `no_std` is meant for
+    let error_impl =
+        (!input.opts.no_std).then(|| quote!(impl ::std::error::Error for #error_name {}));
+
+    quote! {
+        #[doc = #doc]
+        #[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq, ::core::cmp::Eq)]
+        #[non_exhaustive]
+        #vis enum #error_name {
+            /// A required field was not set before calling build().
+            UninitializedField(&'static str),
+            #validation_error
+            #finalize_error
+            #sub_builder_error
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                match self {
+                    Self::UninitializedField(field) => ::core::write!(f, "`{}` is not set", field),
+                    #validation_message
+                    #finalize_message
+                    #sub_builder_message
+                }
+            }
+        }
+
+        #error_impl
+
+        #uninitialized_field
+    }
+}
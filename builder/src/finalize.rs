@@ -0,0 +1,76 @@
+// `#[builder(finalize_with = "path")]`: build() passes the stored value of the field through a
+// function to get the value of the field, so that the builder can hold a configuration for things
+// that are only created when the struct is, such as a connection pool from its URL. With
+// `finalize_with(path = "...", ty = "...")` the setters take and the builder stores `ty` instead
+// of the field type. The function may return the field directly or a `Result` whose error is
+// Display; an error is returned from build() as the FinalizeError variant of the builder error.
+// A `build_fn(async)` build() awaits the functions marked `finalize_with(path = "...", async)`
+// and calls the others as they are. A mutable build() passes a clone of the stored value, so that
+// the builder keeps it when the function fails.
+//
+// Telling the two kinds of return types apart is left to the trait solver: build() declares a
+// local trait that both the field type and `Result<field type, E>` implement.
+//
+//     async fn connect(url: String) -> Result<Pool, Error> { ... }
+//
+//     let __built_pool = match self.pool {
+//         Some(value) => match __Finalized::<Pool>::__finalized(connect(value).await) {
+//             Ok(value) => value,
+//             Err(message) => return Err(Into::into(ServerBuilderError::FinalizeError("pool", message))),
+//         },
+//         None => ...,
+//     };
+
+use crate::{error, BuilderField, BuilderInput};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::ext::IdentExt;
+
+// the local trait, declared in build() when some field has a `finalize_with` function
+pub(crate) fn helper_trait(input: &BuilderInput) -> Option<TokenStream> {
+    if !input.has_finalize() {
+        return None;
+    }
+    let alloc = input.alloc_crate();
+    Some(quote! {
+        trait __Finalized<T> {
+            fn __finalized(self) -> ::core::result::Result<T, #alloc::string::String>;
+        }
+
+        impl<T> __Finalized<T> for T {
+            fn __finalized(self) -> ::core::result::Result<T, #alloc::string::String> {
+                ::core::result::Result::Ok(self)
+            }
+        }
+
+        impl<T, E: ::core::fmt::Display> __Finalized<T> for ::core::result::Result<T, E> {
+            fn __finalized(self) -> ::core::result::Result<T, #alloc::string::String> {
+                match self {
+                    ::core::result::Result::Ok(value) => ::core::result::Result::Ok(value),
+                    ::core::result::Result::Err(err) => ::core::result::Result::Err(
+                        #alloc::string::ToString::to_string(&err),
+                    ),
+                }
+            }
+        }
+    })
+}
+
+// the value of `field` in build() from its stored `value`
+pub(crate) fn value(input: &BuilderInput, field: &BuilderField, value: TokenStream) -> TokenStream {
+    let Some(finalize) = &field.opts.finalize_with else {
+        return value;
+    };
+    let path = &finalize.path;
+    let target = field.target;
+    let await_ = finalize.asyncness.as_ref().map(|_| quote!(.await));
+    let error_name = error::error_name(input);
+    let name_str = field.name.unraw().to_string();
+    let err = input.build_error(quote!(#error_name::FinalizeError(#name_str, message)));
+    quote! {
+        match __Finalized::<#target>::__finalized(#path(#value) #await_) {
+            ::core::result::Result::Ok(value) => value,
+            ::core::result::Result::Err(message) => return #err,
+        }
+    }
+}
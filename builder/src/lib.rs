@@ -1,8 +1,18 @@
+mod attr;
+mod cli;
+mod env;
+mod error;
+mod finalize;
+mod sub_builder;
+mod typestate;
+
+use attr::{DefaultValue, FieldOpts, Finalize, Pattern, StructOpts};
 use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Field, Fields, Ident,
-    MetaList, MetaNameValue, NestedMeta, PathSegment, Result, Type,
+    ext::IdentExt, parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Field,
+    Fields, GenericParam, Generics, Ident, Index, Member, PathSegment, Result, Type, TypePath,
+    Variant, Visibility,
 };
 
 #[proc_macro_derive(Builder, attributes(builder))]
@@ -14,181 +24,1487 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     }
 }
 
+// Everything the code generators need to know about the struct being built. For an enum there is
+// one of these per variant with named fields, building the enum through that variant.
+struct BuilderInput<'a> {
+    opts: StructOpts,
+    name: &'a Ident,
+    variant: Option<&'a Ident>,
+    builder_name: Ident,
+    // of the builder, its error, builder() and build()
+    vis: Visibility,
+    // the `///` comments of the struct, or of the variant
+    docs: Vec<&'a Attribute>,
+    generics: &'a Generics,
+    fields: Vec<BuilderField<'a>>,
+    skipped: Vec<SkippedField>,
+}
+
+impl BuilderInput<'_> {
+    // the path build() constructs: `Name` or `Name::Variant`
+    fn ctor(&self) -> TokenStream {
+        let name = self.name;
+        match self.variant {
+            Some(variant) => quote!(#name::#variant),
+            None => quote!(#name),
+        }
+    }
+
+    // `Name::builder()`, or `Name::variant_builder()` for the builder of an enum variant
+    fn builder_fn(&self) -> Ident {
+        match self.variant {
+            Some(variant) => format_ident!("{}_builder", snake_case(&variant.unraw().to_string())),
+            None => format_ident!("builder"),
+        }
+    }
+
+    // `const` in front of builder(), the setters and build() of a `#[builder(const)]` builder
+    fn constness(&self) -> Option<TokenStream> {
+        self.opts.constness.as_ref().map(|_| quote!(const))
+    }
+
+    // `async` in front of build() with `build_fn(async)`
+    fn asyncness(&self) -> Option<TokenStream> {
+        self.opts.build_async.as_ref().map(|_| quote!(async))
+    }
+
+    // whether some field is built from a nested builder, whose errors the builder error reports
+    fn has_sub_builder(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| matches!(field.kind, FieldType::SubBuilder))
+    }
+
+    // whether some field goes through a `finalize_with` function in build(), which may fail
+    fn has_finalize(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| field.opts.finalize_with.is_some())
+    }
+
+    // The crate providing String to the generated code. `::alloc` needs `extern crate alloc;`, which
+    // std crates rarely declare, so only `#[builder(no_std)]` builders refer to it.
+    fn alloc_crate(&self) -> TokenStream {
+        match self.opts.no_std {
+            true => quote!(::alloc),
+            false => quote!(::std),
+        }
+    }
+
+    // Whether the generated error has a ValidationError variant. A const build() cannot validate,
+    // and its error must not hold a String to be usable in const contexts. Without std, the variant
+    // and its need for `alloc` only come with a validation function.
+    fn has_validation_error(&self) -> bool {
+        self.opts.constness.is_none() && (!self.opts.no_std || self.opts.validate.is_some())
+    }
+
+    // `Err(error)` in build(), converted into a custom error type unless build() is const and
+    // returns the generated error as is
+    fn build_error(&self, error: TokenStream) -> TokenStream {
+        match self.opts.constness {
+            Some(_) => quote!(::core::result::Result::Err(#error)),
+            None => quote!(::core::result::Result::Err(::core::convert::Into::into(#error))),
+        }
+    }
+
+    // The end of build(): binds the values of `fields` to locals named after their members,
+    // computes the skipped fields from them, then constructs the struct from the locals. Fields
+    // that may still fail once set are bound first, so that a mutable build() returns their errors
+    // before it has taken any value out of the builder.
+    fn construct(&self, values: &[TokenStream]) -> (TokenStream, TokenStream) {
+        let (fallible, infallible): (Vec<_>, Vec<_>) = self
+            .fields
+            .iter()
+            .zip(values)
+            .partition(|(field, _)| field.may_fail());
+        let (fields, values): (Vec<_>, Vec<_>) = fallible.into_iter().chain(infallible).unzip();
+        let locals = fields.iter().map(|field| built_local(&field.member));
+        let skipped_locals = self.skipped.iter().map(|field| built_local(&field.member));
+        let skipped_values = self.skipped.iter().map(|field| &field.value);
+        let members = self
+            .fields
+            .iter()
+            .map(|field| &field.member)
+            .chain(self.skipped.iter().map(|field| &field.member));
+        let all_locals = members.clone().map(built_local);
+        let ctor = self.ctor();
+        (
+            // `__built__private` for a field `_private`
+            quote! {
+                #(#[allow(non_snake_case)] let #locals = #values;)*
+                #(#[allow(non_snake_case)] let #skipped_locals = #skipped_values;)*
+            },
+            quote! {
+                #ctor {
+                    #(#members: #all_locals,)*
+                }
+            },
+        )
+    }
+
+    // `#[builder(derive(...))]` and `#[builder(struct_attr(...))]` as attributes of the builder
+    fn builder_attrs(&self) -> TokenStream {
+        let derive = &self.opts.derive;
+        let attrs = &self.opts.struct_attrs;
+        let derive = (!derive.is_empty()).then(|| quote!(#[derive(#(#derive),*)]));
+        let serde = self
+            .opts
+            .serde
+            .as_ref()
+            .map(|_| quote!(#[derive(::serde::Deserialize)]));
+        let doc = match &self.opts.doc {
+            Some(doc) => doc.value(),
+            None => format!(
+                "Builder for [`{}`].{}",
+                self.target_path(),
+                self.fields_doc()
+            ),
+        };
+        quote! {
+            #[doc = #doc]
+            #derive
+            #serde
+            #(#[#attrs])*
+        }
+    }
+
+    // `Name` or `Name::Variant`, for links in the generated docs
+    fn target_path(&self) -> String {
+        match self.variant {
+            Some(variant) => format!("{}::{}", self.name, variant),
+            None => self.name.to_string(),
+        }
+    }
+
+    // the paragraphs listing the required and the optional fields
+    fn fields_doc(&self) -> String {
+        let list = |required: bool| {
+            let names: Vec<_> = self
+                .fields
+                .iter()
+                .filter(|field| field.is_required() == required)
+                .map(|field| format!("`{}`", field.name.unraw()))
+                .collect();
+            names.join(", ")
+        };
+        let mut doc = String::new();
+        for (required, title) in [(true, "Required"), (false, "Optional")] {
+            let names = list(required);
+            if !names.is_empty() {
+                doc += &format!("\n\n{} fields: {}.", title, names);
+            }
+        }
+        doc
+    }
+
+    // rustdoc of builder() and build()
+    fn builder_fn_doc(&self) -> TokenStream {
+        let doc = format!(
+            "Returns a [`{}`] with every field unset.{}",
+            self.builder_name,
+            self.fields_doc()
+        );
+        quote!(#[doc = #doc])
+    }
+
+    fn build_doc(&self) -> TokenStream {
+        let doc = format!(
+            "Builds a [`{}`] from the fields set so far.{}",
+            self.target_path(),
+            self.fields_doc()
+        );
+        quote!(#[doc = #doc])
+    }
+}
+
+// A `#[builder(skip)]` field, which has no setter. build() computes its value once every other
+// field is built.
+struct SkippedField {
+    member: Member,
+    value: TokenStream,
+}
+
+struct BuilderField<'a> {
+    // name of the field in the builder and of its setter: the field's own name, `_0`, `_1`, ... for
+    // tuple fields, or the one given by `#[builder(name = "...")]`
+    name: Ident,
+    // the field in the struct being built
+    member: Member,
+    // of the setters: the field's own, or the builder's for the always public fields of a variant
+    vis: Visibility,
+    // the `///` comments of the field
+    docs: Vec<&'a Attribute>,
+    // what the builder stores and the setters take: the field's type, the `ty` of its
+    // `finalize_with` function, or the builder of a `sub_builder` field
+    ty: Type,
+    // the type of the field in the struct being built
+    target: &'a Type,
+    kind: FieldType,
+    // value for the field when its setter is never called, from `#[builder(default)]` on the field
+    // or on the struct
+    default: Option<TokenStream>,
+    opts: FieldOpts,
+}
+
+impl BuilderField<'_> {
+    // whether build() cannot succeed until this field has been set
+    fn is_required(&self) -> bool {
+        self.unset_value().is_none() && !matches!(self.kind, FieldType::SubBuilder)
+    }
+
+    // whether build() may fail on the field once it is set, through its finalize_with function or
+    // its nested builder
+    fn may_fail(&self) -> bool {
+        self.opts.finalize_with.is_some() || matches!(self.kind, FieldType::SubBuilder)
+    }
+
+    // the value build() gives the field if it was never set, or None if it is required or built
+    // from an empty sub-builder
+    fn unset_value(&self) -> Option<TokenStream> {
+        match (&self.default, &self.kind) {
+            (Some(default), _) => Some(default.clone()),
+            (None, FieldType::OptionType(_)) => Some(quote!(::core::option::Option::None)),
+            (None, FieldType::EachType(..)) => Some(quote!(::core::default::Default::default())),
+            (None, FieldType::RawType | FieldType::SubBuilder) => None,
+        }
+    }
+
+    // whether the setter of an Option field takes the content rather than the Option itself
+    fn strips_option(&self) -> bool {
+        !matches!(self.opts.setter_option, Some((true, _)))
+    }
+
+    // rustdoc of the setters: `#[builder(doc = "...")]`, the field's own, or a generated one so that
+    // `missing_docs` holds for the builder
+    fn setter_docs(&self) -> TokenStream {
+        if let Some(doc) = &self.opts.doc {
+            return quote!(#[doc = #doc]);
+        }
+        if !self.docs.is_empty() {
+            let docs = &self.docs;
+            return quote!(#(#docs)*);
+        }
+        let doc = match &self.kind {
+            FieldType::EachType(..) => format!("Adds an element to `{}`.", self.name.unraw()),
+            _ => format!("Sets `{}`.", self.name.unraw()),
+        };
+        quote!(#[doc = #doc])
+    }
+
+    // the name of the setter storing one value of this field and what that setter takes
+    fn value_setter(&self) -> (&Ident, SetterValue) {
+        match &self.kind {
+            FieldType::OptionType(inner) if self.strips_option() => {
+                (&self.name, SetterValue::Value(quote!(#inner)))
+            }
+            FieldType::EachType(each, EachItem::Value(item)) => {
+                (each, SetterValue::Value(quote!(#item)))
+            }
+            FieldType::EachType(each, EachItem::Entry(key, value)) => {
+                (each, SetterValue::Entry(quote!(#key), quote!(#value)))
+            }
+            _ => {
+                let ty = &self.ty;
+                (&self.name, SetterValue::Value(quote!(#ty)))
+            }
+        }
+    }
+
+    // stores the setter's `value` into the builder `b`, spanned at the field, where a const setter
+    // reports a stored type that needs dropping
+    fn store_value(&self, b: TokenStream) -> TokenStream {
+        let name = &self.name;
+        let b = respan(b, self.target.span());
+        match &self.kind {
+            FieldType::OptionType(_) if self.strips_option() => {
+                quote_spanned! {self.target.span()=>
+                    #b.#name = ::core::option::Option::Some(::core::option::Option::Some(value));
+                }
+            }
+            FieldType::EachType(..) => quote! {
+                ::core::iter::Extend::extend(
+                    #b.#name.get_or_insert_with(::core::default::Default::default),
+                    ::core::iter::once(value),
+                );
+            },
+            _ => quote_spanned! {self.target.span()=>
+                #b.#name = ::core::option::Option::Some(value);
+            },
+        }
+    }
+}
+
+// What a setter takes: a single value, or the key and the value of a map entry as two parameters
+// that are stored as a `(key, value)` tuple.
+enum SetterValue {
+    Value(TokenStream),
+    Entry(TokenStream, TokenStream),
+}
+
+// The signature of a setter for a value of some type `T`: `fn name(value: T)`, or
+// `fn name<__V: Into<T>>(value: __V)` with `setter(into)`, and the fallible
+// `fn try_name<__V: TryInto<T>>(value: __V) -> Result<_, __V::Error>` with `try_setter`.
+struct SetterSig {
+    name: Ident,
+    generics: TokenStream,
+    params: TokenStream,
+    // turns the parameters into `value: T`
+    convert: TokenStream,
+    error: Option<TokenStream>,
+}
+
+fn setter_sigs(field: &BuilderField, name: &Ident, value: &SetterValue) -> Vec<SetterSig> {
+    let into = field.opts.setter_into;
+    let ty = match value {
+        SetterValue::Value(ty) => ty,
+        SetterValue::Entry(key, value) => {
+            // try_setter is rejected for map entries, their two conversions have different errors
+            let (generics, params, convert) = if into {
+                (
+                    quote!(<__K: ::core::convert::Into<#key>, __V: ::core::convert::Into<#value>>),
+                    quote!(key: __K, value: __V),
+                    quote! {
+                        let value = (
+                            ::core::convert::Into::into(key),
+                            ::core::convert::Into::into(value),
+                        );
+                    },
+                )
+            } else {
+                (
+                    TokenStream::new(),
+                    quote!(key: #key, value: #value),
+                    quote!(let value = (key, value);),
+                )
+            };
+            return vec![SetterSig {
+                name: name.clone(),
+                generics,
+                params,
+                convert,
+                error: None,
+            }];
+        }
+    };
+    let mut sigs = vec![if into {
+        SetterSig {
+            name: name.clone(),
+            generics: quote!(<__V: ::core::convert::Into<#ty>>),
+            params: quote!(value: __V),
+            convert: quote!(let value = ::core::convert::Into::into(value);),
+            error: None,
+        }
+    } else {
+        SetterSig {
+            name: name.clone(),
+            generics: TokenStream::new(),
+            params: quote!(value: #ty),
+            convert: TokenStream::new(),
+            error: None,
+        }
+    }];
+    if field.opts.try_setter {
+        sigs.push(SetterSig {
+            name: format_ident!("try_{}", name),
+            generics: quote!(<__V: ::core::convert::TryInto<#ty>>),
+            params: quote!(value: __V),
+            convert: quote!(let value = ::core::convert::TryInto::try_into(value)?;),
+            error: Some(quote!(<__V as ::core::convert::TryInto<#ty>>::Error)),
+        });
+    }
+    sigs
+}
+
+// `SourceGit` -> `source_git`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+// `let __default = ...;` at the start of build() when the struct has a `#[builder(default)]`,
+// whose fields are then used for unset fields without a default of their own
+fn struct_default(input: &BuilderInput) -> Option<TokenStream> {
+    let name = input.name;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let value = match input.opts.default.as_ref()? {
+        DefaultValue::Trait => quote!(<#name #ty_generics as ::core::default::Default>::default()),
+        DefaultValue::Expr(expr) => quote!(#expr),
+    };
+    Some(quote! {
+        let __default: #name #ty_generics = #value;
+    })
+}
+
+// Runs the `build_fn(validate)` function at the start of build() and returns its error as a
+// ValidationError. `builder` is the builder as a reference or a place expression.
+fn validate_call(input: &BuilderInput, builder: TokenStream) -> Option<TokenStream> {
+    let validate = input.opts.validate.as_ref()?;
+    let error_name = error::error_name(input);
+    let alloc = input.alloc_crate();
+    let err = input.build_error(quote! {
+        #error_name::ValidationError(#alloc::string::ToString::to_string(&err))
+    });
+    Some(quote! {
+        if let ::core::result::Result::Err(err) = #validate(#builder) {
+            return #err;
+        }
+    })
+}
+
 fn build_struct_def(tree: DeriveInput) -> Result<TokenStream> {
     let span = tree.span();
-    let  Data::Struct(data) = tree.data else {
-        return Err(Error::new(span, "Builder derive macro only supports struct"));
-    };
-    let Fields::Named(ref fields) = data.fields else {
-        return Err(Error::new(span, "Builder derive macro only supports named fields"));
+    let builder_input =
+        |opts: StructOpts, variant: Option<&Variant>, (fields, skipped)| -> Result<TokenStream> {
+            let attrs = variant.map_or(&tree.attrs, |variant| &variant.attrs);
+            let variant = variant.map(|variant| &variant.ident);
+            let builder_name = match (&opts.name, variant) {
+                (Some(name), _) => name.clone(),
+                (None, Some(variant)) => format_ident!("{}{}Builder", tree.ident, variant),
+                (None, None) => format_ident!("{}Builder", tree.ident),
+            };
+            let vis = opts.vis.clone().unwrap_or_else(|| tree.vis.clone());
+            let input = BuilderInput {
+                opts,
+                name: &tree.ident,
+                variant,
+                builder_name,
+                vis,
+                docs: attrs
+                    .iter()
+                    .filter(|attr| attr.path.is_ident("doc"))
+                    .collect(),
+                generics: &tree.generics,
+                fields,
+                skipped,
+            };
+            expand_builder(&input)
+        };
+
+    match &tree.data {
+        Data::Struct(data) => {
+            let opts = attr::parse_struct_opts(&tree.attrs, &StructOpts::default())?;
+            let fields = builder_fields(&data.fields, &opts, None)?;
+            builder_input(opts, None, fields)
+        }
+        Data::Enum(data) => {
+            let enum_opts = attr::parse_struct_opts(&tree.attrs, &StructOpts::default())?;
+            if let Some(name) = &enum_opts.name {
+                return Err(Error::new_spanned(
+                    name,
+                    "`name` on an enum would give every variant's builder the same name, put it on the variants instead",
+                ));
+            }
+            let variant_vis = enum_opts.vis.as_ref().unwrap_or(&tree.vis);
+            let mut result = TokenStream::new();
+            for variant in data.variants.iter() {
+                if let Fields::Unit = variant.fields {
+                    continue;
+                }
+                let opts = attr::parse_struct_opts(&variant.attrs, &enum_opts)?;
+                if opts.default.is_some() {
+                    return Err(Error::new(
+                        variant.span(),
+                        "`default` is not supported on enums, put it on the fields instead",
+                    ));
+                }
+                let vis = opts.vis.as_ref().unwrap_or(variant_vis);
+                let fields = builder_fields(&variant.fields, &opts, Some(vis))?;
+                result.extend(builder_input(opts, Some(variant), fields)?);
+            }
+            if result.is_empty() {
+                return Err(Error::new(
+                    span,
+                    "Builder derive macro needs an enum variant with fields to build",
+                ));
+            }
+            Ok(result)
+        }
+        Data::Union(_) => Err(Error::new(
+            span,
+            "Builder derive macro only supports structs and enums",
+        )),
+    }
+}
+
+// `vis` is the visibility of the setters of an enum variant, whose fields have none of their own
+fn builder_fields<'a>(
+    fields: &'a Fields,
+    opts: &StructOpts,
+    vis: Option<&Visibility>,
+) -> Result<(Vec<BuilderField<'a>>, Vec<SkippedField>)> {
+    let mut builder_fields = Vec::with_capacity(fields.len());
+    let mut skipped = Vec::new();
+    let members: Vec<Member> = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index {
+                index: index as u32,
+                span: field.ty.span(),
+            }),
+        })
+        .collect();
+    for ((index, field), member) in fields.iter().enumerate().zip(members.iter().cloned()) {
+        let mut field_opts = attr::parse_field_opts(&field.attrs)?;
+        let name = match (field_opts.name.take(), &field.ident) {
+            (Some(name), _) => name,
+            (None, Some(ident)) => ident.clone(),
+            (None, None) => format_ident!("_{}", index),
+        };
+        if opts.constness.is_some() {
+            check_const_field(field, &field_opts)?;
+        }
+        if let (
+            Some(Finalize {
+                asyncness: Some(key),
+                ..
+            }),
+            None,
+        ) = (&field_opts.finalize_with, &opts.build_async)
+        {
+            return Err(Error::new_spanned(
+                key,
+                "an `async` finalize_with function needs `build_fn(async)`",
+            ));
+        }
+        let default = match field_opts.default.take() {
+            Some(DefaultValue::Trait) => Some(quote!(::core::default::Default::default())),
+            Some(DefaultValue::Expr(expr)) => Some(quote!(#expr)),
+            None if opts.default.is_some() => Some(quote!(__default.#member)),
+            None => None,
+        };
+        if let Some(skip) = &field_opts.skip {
+            if field_opts.each.is_some()
+                || field_opts.setter_into
+                || field_opts.setter_option.is_some()
+                || field_opts.try_setter
+                || field_opts.presence.is_some()
+                || field_opts.name.is_some()
+                || field_opts.finalize_with.is_some()
+                || field_opts.sub_builder.is_some()
+            {
+                return Err(Error::new_spanned(
+                    skip,
+                    "a `skip` field has no setter, only `default` applies to it",
+                ));
+            }
+            let value = default.unwrap_or_else(|| quote!(::core::default::Default::default()));
+            skipped.push(SkippedField {
+                value: refer_to_built(value, &members),
+                member,
+            });
+            continue;
+        }
+        let ty = match (&field_opts.finalize_with, &field_opts.sub_builder) {
+            (Some(Finalize { ty: Some(ty), .. }), _) => ty.clone(),
+            (_, Some((_, Some(ty)))) => ty.clone(),
+            (_, Some((key, None))) => sub_builder::builder_type(&field.ty).ok_or_else(|| {
+                Error::new_spanned(
+                    key,
+                    r#"`sub_builder` needs a struct type deriving Builder, or the builder type as `sub_builder = "..."`"#,
+                )
+            })?,
+            _ => field.ty.clone(),
+        };
+        let kind = check_field_type(&ty, &field_opts)?;
+        if let (true, FieldType::EachType(each, EachItem::Entry(..))) =
+            (field_opts.try_setter, &kind)
+        {
+            return Err(Error::new_spanned(
+                each,
+                "`try_setter` is not supported for the key-value setter of a map",
+            ));
+        }
+        if let Some((_, key)) = &field_opts.setter_option {
+            if !matches!(kind, FieldType::OptionType(_)) {
+                return Err(Error::new_spanned(
+                    key,
+                    "`option` and `strip_option` only apply to fields of type Option",
+                ));
+            }
+        }
+        builder_fields.push(BuilderField {
+            name,
+            member,
+            vis: vis.unwrap_or(&field.vis).clone(),
+            docs: field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path.is_ident("doc"))
+                .collect(),
+            ty,
+            target: &field.ty,
+            kind,
+            default,
+            opts: field_opts,
+        });
+    }
+    Ok((builder_fields, skipped))
+}
+
+// Rejects what a `#[builder(const)]` builder cannot do for this field in a const fn: anything that
+// goes through a trait, such as `Default`, `Into` or `Extend`.
+fn check_const_field(field: &Field, opts: &FieldOpts) -> Result<()> {
+    let unsupported = if let Some(each) = &opts.each {
+        let key = &each.key;
+        Some((quote!(#key), "`each`"))
+    } else if opts.setter_into {
+        Some((quote!(#field), "`setter(into)`"))
+    } else if opts.try_setter {
+        Some((quote!(#field), "`try_setter`"))
+    } else if let Some(finalize) = &opts.finalize_with {
+        let key = &finalize.key;
+        Some((quote!(#key), "`finalize_with`"))
+    } else if let Some((key, _)) = &opts.sub_builder {
+        Some((quote!(#key), "`sub_builder`"))
+    } else {
+        match (&opts.default, &opts.skip) {
+            (Some(DefaultValue::Trait), _) | (None, Some(_)) => Some((
+                quote!(#field),
+                r#"`default` through the Default trait, use `default = "..."` with a const expression"#,
+            )),
+            _ => None,
+        }
     };
-    let vec_len = fields.named.len();
+    match unsupported {
+        Some((tokens, unsupported)) => Err(Error::new_spanned(
+            tokens,
+            format!("`const` builders do not support {}", unsupported),
+        )),
+        None => Ok(()),
+    }
+}
+
+// A const build() moves the fields out of the builder into locals first, spanned at the field
+// types: a stored type that needs dropping is then reported at its field.
+fn destructure(input: &BuilderInput) -> Option<TokenStream> {
+    input.opts.constness.as_ref()?;
+    let names = input.fields.iter().map(|field| &field.name);
+    let locals = input.fields.iter().map(stored_local);
+    let receiver = const_build_receiver(input);
+    Some(quote! {
+        let Self { #(#names: #locals,)* .. } = #receiver;
+    })
+}
+
+// The receiver of a const setter, spanned at the field: a setter overwriting a value that needs
+// dropping would drop the builder if that panicked, which is reported at the receiver.
+fn const_receiver(
+    input: &BuilderInput,
+    field: &BuilderField,
+    receiver: TokenStream,
+) -> TokenStream {
+    match input.opts.constness {
+        Some(_) => respan(receiver, field.target.span()),
+        None => receiver,
+    }
+}
+
+// The receiver of a const build(), spanned at the struct: the builder as a whole still needs
+// dropping when one of its fields does, which the fields themselves are reported for.
+fn const_build_receiver(input: &BuilderInput) -> TokenStream {
+    respan(quote!(self), input.name.span())
+}
+
+// `tokens` pointing at `span` in errors
+fn respan(tokens: TokenStream, span: proc_macro2::Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+// the local holding the stored value of a field in a const build()
+fn stored_local(field: &BuilderField) -> Ident {
+    Ident::new(
+        &format!("__stored_{}", field.name.unraw()),
+        field.target.span(),
+    )
+}
+
+// the local holding the built value of a field in build()
+fn built_local(member: &Member) -> Ident {
+    match member {
+        Member::Named(ident) => format_ident!("__built_{}", ident.unraw()),
+        Member::Unnamed(index) => format_ident!("__built_{}", index.index),
+    }
+}
+
+// Replaces `self.field` in the default of a skipped field with the local holding the value built
+// for that field, so that the default can be computed from the other fields.
+fn refer_to_built(tokens: TokenStream, members: &[Member]) -> TokenStream {
+    use proc_macro2::{Group, TokenTree};
+
+    let mut tokens = tokens.into_iter();
+    let mut result = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), refer_to_built(group.stream(), members));
+                replaced.set_span(group.span());
+                result.push(TokenTree::Group(replaced));
+            }
+            TokenTree::Ident(ident) if ident == "self" => {
+                let mut lookahead = tokens.clone();
+                let member = match (lookahead.next(), lookahead.next()) {
+                    (Some(TokenTree::Punct(dot)), Some(next)) if dot.as_char() == '.' => {
+                        members.iter().find(|member| match (member, &next) {
+                            (Member::Named(name), TokenTree::Ident(ident)) => name == ident,
+                            (Member::Unnamed(index), TokenTree::Literal(lit)) => {
+                                lit.to_string() == index.index.to_string()
+                            }
+                            _ => false,
+                        })
+                    }
+                    _ => None,
+                };
+                match member {
+                    Some(member) => {
+                        tokens = lookahead;
+                        let mut local = built_local(member);
+                        local.set_span(ident.span());
+                        result.push(TokenTree::Ident(local));
+                    }
+                    None => result.push(TokenTree::Ident(ident)),
+                }
+            }
+            token => result.push(token),
+        }
+    }
+    result.into_iter().collect()
+}
+
+fn expand_builder(input: &BuilderInput) -> Result<TokenStream> {
+    if input.opts.typestate {
+        if let (Some(error), None, false) = (
+            &input.opts.error,
+            &input.opts.validate,
+            input.has_finalize(),
+        ) {
+            return Err(Error::new_spanned(
+                error,
+                "`error` has no effect with `typestate` unless `build_fn(validate)` or `finalize_with` can fail build()",
+            ));
+        }
+        if let Some(serde) = &input.opts.serde {
+            return Err(Error::new_spanned(
+                serde,
+                "`serde` is not supported with `typestate`, whose builder tracks set fields in its type",
+            ));
+        }
+        if let Some(cli) = &input.opts.cli {
+            return Err(Error::new_spanned(
+                cli,
+                "`cli` is not supported with `typestate`, whose builder changes type with every flag",
+            ));
+        }
+        if let Some(prefix) = &input.opts.env_prefix {
+            return Err(Error::new_spanned(
+                prefix,
+                "`env_prefix` is not supported with `typestate`, whose builder changes type with every field set",
+            ));
+        }
+        if let Some(getters) = &input.opts.getters {
+            return Err(Error::new_spanned(
+                getters,
+                "`getters` is not supported with `typestate`, whose builder tells the fields that are set by its type",
+            ));
+        }
+        if let Some((key, _)) = input
+            .fields
+            .iter()
+            .find_map(|field| field.opts.sub_builder.as_ref())
+        {
+            return Err(Error::new_spanned(
+                key,
+                "`sub_builder` is not supported with `typestate`, which cannot hand out a builder to change in place",
+            ));
+        }
+        return Ok(typestate::expand(input));
+    }
+    if let (Some(cli), true) = (&input.opts.cli, input.opts.no_std) {
+        return Err(Error::new_spanned(
+            cli,
+            "`cli` is not supported with `no_std`, command line arguments come from std",
+        ));
+    }
+    if let (Some(prefix), true) = (&input.opts.env_prefix, input.opts.no_std) {
+        return Err(Error::new_spanned(
+            prefix,
+            "`env_prefix` is not supported with `no_std`, environment variables come from std",
+        ));
+    }
+    let cli = input
+        .opts
+        .cli
+        .as_ref()
+        .map(|_| cli::expand(input))
+        .transpose()?;
+    let env = env::expand(input)?;
+    let mut tokens = expand(input);
+    tokens.extend(cli);
+    tokens.extend(env);
+    Ok(tokens)
+}
+
+fn expand(input: &BuilderInput) -> TokenStream {
+    let error_name = error::error_name(input);
+    let vec_len = input.fields.len();
     let mut field_list = Vec::with_capacity(vec_len);
     let mut builder_init_list = Vec::with_capacity(vec_len);
     let mut method_list = Vec::with_capacity(vec_len);
-    let mut assign_field = Vec::with_capacity(vec_len);
-
-    for field in fields.named.iter() {
-        if let Some(ref name) = field.ident {
-            let ty = &field.ty;
-            match check_field_type(field)? {
-                FieldType::OptionType(raw_ty) => {
-                    builder_init_list.push(quote! {
-                        #name: None,
-                    });
-                    field_list.push(quote! {
-                        #name: ::core::option::Option<#raw_ty>,
-                    });
-                    assign_field.push(quote! {
-                        #name: self.#name.clone(),
-                    });
-                    method_list.push(quote! {
-                        fn #name (&mut self, value: #raw_ty) -> &mut Self {
-                            self.#name = ::core::option::Option::Some(value);
-                            self
-                        }
-                    });
+    let mut values = Vec::with_capacity(vec_len);
+    let mut checks = Vec::new();
+
+    for field in input.fields.iter() {
+        let (name, ty) = (&field.name, &field.ty);
+        let name_str = name.unraw().to_string();
+        builder_init_list.push(quote! {
+            #name: ::core::option::Option::None,
+        });
+        // keep the keys of the struct when the setter is renamed
+        let rename = match (&input.opts.serde, &field.member) {
+            (Some(_), Member::Named(member)) if member != name => {
+                let member = member.unraw().to_string();
+                Some(quote!(#[serde(rename = #member)]))
+            }
+            _ => None,
+        };
+        field_list.push(quote! {
+            #rename
+            #name: ::core::option::Option<#ty>,
+        });
+        // A mutable build() takes nothing out of the builder before all that could fail has: the
+        // required fields are checked first, and the values that may fail are built from clones.
+        let stored = match input.opts.pattern() {
+            Pattern::Owned if input.opts.constness.is_some() => {
+                let stored = stored_local(field);
+                quote!(#stored)
+            }
+            Pattern::Owned => quote!(self.#name),
+            // a missing Clone is reported at the field
+            Pattern::Mutable if field.may_fail() => {
+                quote_spanned!(field.ty.span()=> ::core::clone::Clone::clone(&self.#name))
+            }
+            Pattern::Mutable => quote!(self.#name.take()),
+            Pattern::Immutable => quote!(::core::clone::Clone::clone(&self.#name)),
+        };
+        let err = input.build_error(quote!(#error_name::UninitializedField(#name_str)));
+        if input.opts.pattern() == Pattern::Mutable && field.is_required() {
+            checks.push(quote! {
+                if ::core::option::Option::is_none(&self.#name) {
+                    return #err;
                 }
-                FieldType::VecType((raw_ty, Some(each))) => {
-                    // check whether there is an argument "builder"
-                    builder_init_list.push(quote! {
-                        #name:  std::vec::Vec::new(),
-                    });
-                    field_list.push(quote! {
-                        #name: #ty,
-                    });
-                    method_list.push(quote! {
-                        fn #each (&mut self, value: #raw_ty) -> &mut Self {
-                            self.#name.push(value);
-                            self
-                        }
-                    });
-                    assign_field.push(quote! {
-                        #name: self.#name.drain(..).collect(),
-                    });
+            });
+        }
+        let unset = field.unset_value().unwrap_or_else(|| quote!(return #err));
+        if let FieldType::SubBuilder = field.kind {
+            values.push(sub_builder::value(input, field, stored));
+            method_list.push(sub_builder::accessor(field));
+            method_list.push(clear_setter(input, field));
+            continue;
+        }
+        let value = finalize::value(input, field, quote!(value));
+        values.push(quote! {
+            match #stored {
+                ::core::option::Option::Some(value) => #value,
+                ::core::option::Option::None => #unset,
+            }
+        });
+        method_list.extend(field_setters(input, field));
+    }
+
+    if let Some(phantom) = phantom_type(input.generics) {
+        let skip = input.opts.serde.as_ref().map(|_| quote!(#[serde(skip)]));
+        field_list.push(quote! {
+            #skip
+            __phantom: #phantom,
+        });
+        builder_init_list.push(quote! {
+            __phantom: ::core::marker::PhantomData,
+        });
+    }
+
+    let name = input.name;
+    let builder_name = &input.builder_name;
+    let generics = input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let vis = &input.vis;
+    let names: Vec<_> = input.fields.iter().map(|field| &field.name).collect();
+    let merges = input.fields.iter().map(|field| {
+        let name = &field.name;
+        match field.kind {
+            FieldType::SubBuilder => sub_builder::merge(field),
+            _ => quote! {
+                if let ::core::option::Option::Some(value) = other.#name {
+                    self.#name = ::core::option::Option::Some(value);
                 }
-                FieldType::RawType(_) | FieldType::VecType(_) => {
-                    builder_init_list.push(quote! {
-                        #name: None,
-                    });
-                    field_list.push(quote! {
-                        #name: ::core::option::Option<#ty>,
-                    });
-                    assign_field.push(quote! {
-                        #name: self.#name.take().ok_or(concat!(stringify!(#name), "is not set"))?,
-                    });
-                    method_list.push(quote! {
-                        fn #name (&mut self, value: #ty) -> &mut Self {
-                            self.#name = ::core::option::Option::Some(value);
-                            self
-                        }
-                    });
+            },
+        }
+    });
+    let getters = getters(input);
+    let merge_fn = quote! {
+        /// Takes the value of every field that is set in `other`, such as a layer of
+        /// configuration loaded later or the fields to change in a copy of a value.
+        #vis fn merge(&mut self, other: Self) -> &mut Self {
+            #(#merges)*
+            self
+        }
+    };
+    // Starting from an existing value only makes sense for a struct: the value of an enum may be
+    // another variant than the one of the builder. Skipped fields are computed again by build(),
+    // but a `finalize_with` function cannot be undone to get back the value the builder stores.
+    let from_value = (input.variant.is_none() && !input.has_finalize()).then(|| {
+        // a sub-builder starts from the nested value through its own From
+        let values = input.fields.iter().map(|field| {
+            let member = &field.member;
+            match field.kind {
+                FieldType::SubBuilder => quote!(::core::convert::From::from(value.#member)),
+                _ => quote!(value.#member),
+            }
+        });
+        let phantom = phantom_type(generics).map(|_| quote!(__phantom: ::core::marker::PhantomData,));
+        quote! {
+            impl #impl_generics ::core::convert::From<#name #ty_generics> for #builder_name #ty_generics #where_clause {
+                fn from(value: #name #ty_generics) -> Self {
+                    #builder_name {
+                        #(#names: ::core::option::Option::Some(#values),)*
+                        #phantom
+                    }
                 }
-                FieldType::UnsupportedType => {
-                    return Err(Error::new(field.span(), "unsupported field type"));
+            }
+
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// A builder set to a copy of every field of `self`.
+                // the bound is made higher-ranked so that it is not rejected as trivially false
+                // when the struct is not Clone; the method is then simply unavailable
+                #vis fn to_builder(&self) -> #builder_name #ty_generics
+                where
+                    for<'__b> Self: ::core::clone::Clone,
+                {
+                    ::core::convert::From::from(::core::clone::Clone::clone(self))
                 }
             }
         }
-    }
-
-    let name = tree.ident;
-    let builder_name = Ident::new(&format!("{}Builder", name), name.span());
+    });
+    let error_ty = error::build_error_type(input);
+    let error_def = error::expand(input);
+    let struct_default = struct_default(input);
+    let destructure = destructure(input);
+    let builder_fn = input.builder_fn();
+    let (bindings, construct) = input.construct(&values);
+    let builder_attrs = input.builder_attrs();
+    let constness = input.constness();
+    let builder_fn_doc = input.builder_fn_doc();
+    let build_doc = input.build_doc();
+    let asyncness = input.asyncness();
+    let finalize_trait = finalize::helper_trait(input);
+    let sub_builder_trait = sub_builder::helper_trait(input);
+    let (build_receiver, validate) = match input.opts.pattern() {
+        Pattern::Owned if input.opts.constness.is_some() => (
+            const_build_receiver(input),
+            validate_call(input, quote!(&self)),
+        ),
+        Pattern::Owned => (quote!(self), validate_call(input, quote!(&self))),
+        Pattern::Mutable => (quote!(&mut self), validate_call(input, quote!(self))),
+        Pattern::Immutable => (quote!(&self), validate_call(input, quote!(self))),
+    };
     let result = quote! {
-        impl #name {
-            pub fn builder() -> #builder_name {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #builder_fn_doc
+            #vis #constness fn #builder_fn() -> #builder_name #ty_generics {
                 #builder_name {
                     #(#builder_init_list)*
                 }
             }
         }
 
-        pub struct #builder_name {
+        #builder_attrs
+        #vis struct #builder_name #generics #where_clause {
             #(#field_list)*
         }
 
-        impl #builder_name {
+        impl #impl_generics #builder_name #ty_generics #where_clause {
             #(#method_list)*
 
-            pub fn build(&mut self) -> std::result::Result<#name, std::boxed::Box<dyn std::error::Error>> {
-                std::result::Result::Ok(#name {
-                    #(#assign_field)*
-                })
+            #getters
+
+            #merge_fn
+
+            #build_doc
+            #vis #constness #asyncness fn build(#build_receiver) -> ::core::result::Result<#name #ty_generics, #error_ty> {
+                #finalize_trait
+                #sub_builder_trait
+                #validate
+                #struct_default
+                #(#checks)*
+                #destructure
+                #bindings
+                ::core::result::Result::Ok(#construct)
             }
         }
+
+        #from_value
+
+        #error_def
     };
-    Ok(result)
+    result
+}
+
+// The text of a command line flag or an environment variable in the String local `var`, parsed
+// with FromStr into a local of the same name, for `cli` and `env_prefix`. `invalid(value, message)`
+// is the error returned from the enclosing function when the text does not parse.
+fn parse_text(
+    var: &str,
+    ty: &TokenStream,
+    invalid: &dyn Fn(TokenStream, TokenStream) -> TokenStream,
+) -> TokenStream {
+    let var = format_ident!("{}", var);
+    let err = invalid(
+        quote!(#var),
+        quote!(::std::string::ToString::to_string(&err)),
+    );
+    quote! {
+        let #var: #ty = match ::core::str::FromStr::from_str(&#var) {
+            ::core::result::Result::Ok(parsed) => parsed,
+            ::core::result::Result::Err(err) => return ::core::result::Result::Err(#err),
+        };
+    }
 }
 
-#[allow(clippy::enum_variant_names)]
+// Like parse_text(), the text in `value` as an element of an `each` collection. The entries of a
+// map are written `KEY=VALUE`.
+fn parse_each_item(
+    item: &EachItem,
+    invalid: &dyn Fn(TokenStream, TokenStream) -> TokenStream,
+) -> TokenStream {
+    let (key_ty, value_ty) = match item {
+        EachItem::Value(ty) => return parse_text("value", &quote!(#ty), invalid),
+        EachItem::Entry(key, value) => (key, value),
+    };
+    let not_entry = invalid(
+        quote!(value),
+        quote!(::std::string::ToString::to_string("expected KEY=VALUE")),
+    );
+    let parse_key = parse_text("key", &quote!(#key_ty), invalid);
+    let parse_value = parse_text("value", &quote!(#value_ty), invalid);
+    quote! {
+        let (key, value) = match value.find('=') {
+            ::core::option::Option::Some(at) => (
+                ::std::string::ToString::to_string(&value[..at]),
+                ::std::string::ToString::to_string(&value[at + 1..]),
+            ),
+            ::core::option::Option::None => return ::core::result::Result::Err(#not_entry),
+        };
+        #parse_key
+        #parse_value
+        let value = (key, value);
+    }
+}
+
+// `#[builder(getters)]`: `get_x()` and `is_x_set()` for every field, and `unset_fields()` listing
+// the required fields that build() would still report as missing. The getters are prefixed so that
+// they do not clash with the setters.
+fn getters(input: &BuilderInput) -> Option<TokenStream> {
+    input.opts.getters.as_ref()?;
+    let alloc = input.alloc_crate();
+    let getters = input.fields.iter().map(|field| {
+        let (name, vis, ty) = (&field.name, &field.vis, &field.ty);
+        let field_name = name.unraw();
+        let getter = format_ident!("get_{}", field_name);
+        let is_set = format_ident!("is_{}_set", field_name);
+        let getter_doc = format!("The value of `{}`, if it has been set.", field_name);
+        let is_set_doc = format!("Whether `{}` has been set.", field_name);
+        quote! {
+            #[doc = #getter_doc]
+            #[allow(non_snake_case)]
+            #vis fn #getter(&self) -> ::core::option::Option<&#ty> {
+                ::core::option::Option::as_ref(&self.#name)
+            }
+
+            #[doc = #is_set_doc]
+            #[allow(non_snake_case)]
+            #vis fn #is_set(&self) -> bool {
+                ::core::option::Option::is_some(&self.#name)
+            }
+        }
+    });
+    let required = input.fields.iter().filter(|field| field.is_required());
+    let names = required.clone().map(|field| &field.name);
+    let name_strs = required.map(|field| field.name.unraw().to_string());
+    let vis = &input.vis;
+    Some(quote! {
+        #(#getters)*
+
+        /// The required fields that have not been set yet, which build() would report as missing.
+        #vis fn unset_fields(&self) -> #alloc::vec::Vec<&'static str> {
+            // nothing is pushed when every field is optional
+            #[allow(unused_mut)]
+            let mut unset = #alloc::vec::Vec::new();
+            #(
+                if ::core::option::Option::is_none(&self.#names) {
+                    unset.push(#name_strs);
+                }
+            )*
+            unset
+        }
+    })
+}
+
+// The receiver, return type and returned builder of a setter taking the builder in the struct's
+// pattern, and for an immutable builder the copy of `self` that the setter stores into.
+fn setter_frame(
+    input: &BuilderInput,
+) -> (TokenStream, TokenStream, TokenStream, Option<TokenStream>) {
+    let pattern = input.opts.pattern();
+    let (receiver, ret, builder) = match pattern {
+        Pattern::Owned => (quote!(mut self), quote!(Self), quote!(self)),
+        Pattern::Mutable => (quote!(&mut self), quote!(&mut Self), quote!(self)),
+        Pattern::Immutable => (quote!(&self), quote!(Self), quote!(builder)),
+    };
+    let copy = (pattern == Pattern::Immutable).then(|| {
+        let fields = input.fields.iter().map(|field| &field.name);
+        let phantom = phantom_type(input.generics).map(|_| quote!(__phantom: self.__phantom,));
+        quote! {
+            let mut builder = Self {
+                #(#fields: ::core::clone::Clone::clone(&self.#fields),)*
+                #phantom
+            };
+        }
+    });
+    (receiver, ret, builder, copy)
+}
+
+// The setters storing one value of `field`, taking the builder in the struct's pattern, followed
+// by the bulk setters of an `each` field and `clear_x()`. Immutable builders store the value into
+// a copy of `self` that the setter returns.
+fn field_setters(input: &BuilderInput, field: &BuilderField) -> Vec<TokenStream> {
+    let vis = &field.vis;
+    let constness = input.constness();
+    let docs = field.setter_docs();
+    let (receiver, ret, builder, copy) = setter_frame(input);
+    let receiver = const_receiver(input, field, receiver);
+    let store = field.store_value(builder.clone());
+
+    let (name, value) = field.value_setter();
+    let mut setters: Vec<_> = setter_sigs(field, name, &value)
+        .into_iter()
+        .map(|sig| {
+            let SetterSig {
+                name,
+                generics,
+                params,
+                convert,
+                error,
+            } = sig;
+            let (ret, result) = match error {
+                Some(error) => (
+                    quote!(::core::result::Result<#ret, #error>),
+                    quote!(::core::result::Result::Ok(#builder)),
+                ),
+                None => (ret.clone(), builder.clone()),
+            };
+            quote! {
+                #docs
+                #vis #constness fn #name #generics (#receiver, #params) -> #ret {
+                    #convert
+                    #copy
+                    #store
+                    #result
+                }
+            }
+        })
+        .collect();
+    setters.extend(bulk_setters(input, field));
+    setters.push(clear_setter(input, field));
+    setters
+}
+
+// For an `each` field, the setter adding every element of an iterator, named after the field or
+// `extend_x` when the one-element setter already is, and `set_x` replacing the collection.
+fn bulk_setters(input: &BuilderInput, field: &BuilderField) -> Option<TokenStream> {
+    let FieldType::EachType(each, item) = &field.kind else {
+        return None;
+    };
+    let (name, vis) = (&field.name, &field.vis);
+    let (receiver, ret, builder, copy) = setter_frame(input);
+    let extend = match name == each {
+        true => format_ident!("extend_{}", name.unraw()),
+        false => name.clone(),
+    };
+    let set = format_ident!("set_{}", name.unraw());
+    // the elements are converted one by one with `setter(into)`, entries of a map are taken as is
+    let (bound, values) = match item {
+        EachItem::Value(item) if field.opts.setter_into => (
+            quote!(__I::Item: ::core::convert::Into<#item>),
+            quote!(::core::iter::Iterator::map(
+                ::core::iter::IntoIterator::into_iter(values),
+                ::core::convert::Into::into,
+            )),
+        ),
+        EachItem::Value(item) => (
+            quote!(__I: ::core::iter::IntoIterator<Item = #item>),
+            quote!(values),
+        ),
+        EachItem::Entry(key, value) => (
+            quote!(__I: ::core::iter::IntoIterator<Item = (#key, #value)>),
+            quote!(values),
+        ),
+    };
+    let field_name = name.unraw();
+    let extend_doc = format!("Adds every element of `values` to `{}`.", field_name);
+    let set_doc = format!("Replaces `{}` with the elements of `values`.", field_name);
+    Some(quote! {
+        #[doc = #extend_doc]
+        #[allow(non_snake_case)]
+        #vis fn #extend<__I: ::core::iter::IntoIterator>(#receiver, values: __I) -> #ret
+        where
+            #bound,
+        {
+            #copy
+            ::core::iter::Extend::extend(
+                #builder.#name.get_or_insert_with(::core::default::Default::default),
+                #values,
+            );
+            #builder
+        }
+
+        #[doc = #set_doc]
+        #[allow(non_snake_case)]
+        #vis fn #set<__I: ::core::iter::IntoIterator>(#receiver, values: __I) -> #ret
+        where
+            #bound,
+        {
+            #copy
+            let mut collection = ::core::default::Default::default();
+            ::core::iter::Extend::extend(&mut collection, #values);
+            #builder.#name = ::core::option::Option::Some(collection);
+            #builder
+        }
+    })
+}
+
+// `clear_x()`, unsetting the field as if its setter had never been called. Like the getters and the
+// bulk setters, it is `clear__private` for a field `_private`, which is not snake case.
+fn clear_setter(input: &BuilderInput, field: &BuilderField) -> TokenStream {
+    let (name, vis) = (&field.name, &field.vis);
+    let constness = input.constness();
+    let (receiver, ret, builder, copy) = setter_frame(input);
+    let receiver = const_receiver(input, field, receiver);
+    let clear = format_ident!("clear_{}", name.unraw());
+    let doc = format!("Unsets `{}`, as if it had never been set.", name.unraw());
+    let place = respan(quote!(#builder.#name), field.target.span());
+    let clear_value = quote_spanned! {field.target.span()=>
+        #place = ::core::option::Option::None;
+    };
+    quote! {
+        #[doc = #doc]
+        #[allow(non_snake_case)]
+        #vis #constness fn #clear(#receiver) -> #ret {
+            #copy
+            #clear_value
+            #builder
+        }
+    }
+}
+
+// short-lived values of the expansion, not worth boxing
+#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
 enum FieldType {
     OptionType(Type),
-    // (inner_type, Option<builder(each = "ident")>)
-    VecType((Type, Option<Ident>)),
-    RawType(Type),
-    UnsupportedType,
-}
-
-fn check_field_type(field: &Field) -> Result<FieldType> {
-    let ty = &field.ty;
-    use syn::{AngleBracketedGenericArguments, GenericArgument, Path, PathArguments, TypePath};
-    use FieldType::*;
-
-    if let syn::Type::Path(TypePath {
-        path: Path { segments, .. },
-        ..
-    }) = ty
-    {
-        if let Some(&PathSegment {
-            ref ident,
-            arguments:
-                PathArguments::AngleBracketed(AngleBracketedGenericArguments { ref args, .. }),
-        }) = segments.first()
-        {
-            if let (1, Some(GenericArgument::Type(t))) = (args.len(), args.first()) {
-                return Ok(match ident.to_string().as_str() {
-                    "Vec" => VecType((t.clone(), get_builder_each(&field.attrs)?)),
-                    "Option" => OptionType(t.clone()),
-                    _ => UnsupportedType,
-                });
+    // a collection filled by the `#[builder(each = "...")]` setter of the given name
+    EachType(Ident, EachItem),
+    RawType,
+    // a `#[builder(sub_builder)]` field, stored as its builder
+    SubBuilder,
+}
+
+// what the `each` setter adds to its collection
+#[allow(clippy::large_enum_variant)]
+enum EachItem {
+    Value(Type),
+    // maps take an entry as a key and a value
+    Entry(Type, Type),
+}
+
+// The builder only stores values of the field types, so a type or lifetime parameter that appears
+// in no field (or only inside an `each` collection the builder does not keep as-is) would be
+// rejected as unused. Mention every one of them in a PhantomData marker instead. `fn() -> T`
+// keeps the builder's auto traits and drop check independent of `T`. Const parameters may go
+// unused, so they are left out.
+pub(crate) fn phantom_type(generics: &Generics) -> Option<TokenStream> {
+    let params: Vec<_> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(lt) => {
+                let lt = &lt.lifetime;
+                Some(quote!(&#lt ()))
             }
-        }
+            GenericParam::Type(ty) => {
+                let ident = &ty.ident;
+                Some(quote!(fn() -> #ident))
+            }
+            GenericParam::Const(_) => None,
+        })
+        .collect();
+    if params.is_empty() {
+        return None;
     }
-    Ok(RawType(ty.clone()))
+    Some(quote!(::core::marker::PhantomData<(#(#params,)*)>))
 }
 
-// check whether a builder(each = "name") attribute is annotated
-// if user provides something like #[builder(eac = "arg")], then we should report an error instead
-// of ignoring it
-fn get_builder_each(attrs: &[Attribute]) -> Result<Option<Ident>> {
-    for attr in attrs.iter() {
-        let Ok(meta) = attr.parse_meta() else {
-            return Ok(None);
+// `ty` is the type the builder stores for the field
+fn check_field_type(ty: &Type, opts: &FieldOpts) -> Result<FieldType> {
+    if let Some((key, _)) = &opts.sub_builder {
+        if opts.each.is_some()
+            || opts.setter_into
+            || opts.setter_option.is_some()
+            || opts.try_setter
+            || opts.presence.is_some()
+            || opts.finalize_with.is_some()
+        {
+            return Err(Error::new_spanned(
+                key,
+                "`sub_builder` replaces the setters of the field, only `default`, `name` and `doc` apply to it",
+            ));
+        }
+        return Ok(FieldType::SubBuilder);
+    }
+    if let Some(each) = &opts.each {
+        if let Some((_, key)) = &opts.presence {
+            return Err(Error::new_spanned(
+                key,
+                format!("`{}` cannot be combined with `each`", key),
+            ));
+        }
+        let item = match &each.ty {
+            Some(item) => EachItem::Value(item.clone()),
+            None => collection_item(ty).ok_or_else(|| {
+                Error::new_spanned(
+                    &each.key,
+                    r#"`each` needs a collection such as `Vec<T>` or `HashMap<K, V>`, give the element type of other collections with `each(name = "...", ty = "...")`"#,
+                )
+            })?,
         };
-        let meta_span = meta.span();
-        match meta {
-            syn::Meta::List(MetaList { path, nested, .. }) if path.is_ident("builder") => {
-                if let Some(NestedMeta::Meta(syn::Meta::NameValue(MetaNameValue {
-                    lit,
-                    path,
-                    ..
-                }))) = nested.first()
-                {
-                    match lit {
-                        syn::Lit::Str(s) if path.is_ident("each") => {
-                            return Ok(Some(format_ident!("{}", s.value())));
-                        }
-                        // if the stuff inside builder is not each, report an error
-                        _ => {
-                            return Err(Error::new(
-                                meta_span,
-                                r#"expected `builder(each = "...")`"#,
-                            ))
-                        }
-                    };
-                }
+        return Ok(FieldType::EachType(each.name.clone(), item));
+    }
+    Ok(match &opts.presence {
+        Some((true, key)) => {
+            match generic_type(ty) {
+                Some((_, args)) if args.len() == 1 => FieldType::OptionType(args[0].clone()),
+                _ => return Err(Error::new_spanned(
+                    key,
+                    "`optional` needs a type with exactly one type argument, such as `Maybe<T>`",
+                )),
             }
-            _ => continue,
         }
+        Some((false, _)) => FieldType::RawType,
+        None => match std_type(ty) {
+            Some((ident, args)) if ident == "Option" && args.len() == 1 => {
+                FieldType::OptionType(args[0].clone())
+            }
+            _ => FieldType::RawType,
+        },
+    })
+}
+
+// The element of the standard collections, all of which implement Extend and Default. Other
+// collections need the element type spelled out in the attribute.
+fn collection_item(ty: &Type) -> Option<EachItem> {
+    let (ident, args) = std_type(ty)?;
+    match (ident.to_string().as_str(), args.as_slice()) {
+        ("Vec" | "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet" | "BinaryHeap", [item, ..]) => {
+            Some(EachItem::Value((*item).clone()))
+        }
+        ("HashMap" | "BTreeMap", [key, value, ..]) => {
+            Some(EachItem::Entry((*key).clone(), (*value).clone()))
+        }
+        _ => None,
     }
-    Ok(None)
+}
+
+// `Name<A, B>` as the name and its type arguments, wherever the type comes from
+fn generic_type(ty: &Type) -> Option<(&Ident, Vec<&Type>)> {
+    use syn::{AngleBracketedGenericArguments, GenericArgument, PathArguments};
+
+    let syn::Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let PathSegment {
+        ident,
+        arguments: PathArguments::AngleBracketed(AngleBracketedGenericArguments { args, .. }),
+    } = path.segments.last()?
+    else {
+        return None;
+    };
+    let args = args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect();
+    Some((ident, args))
+}
+
+// Like generic_type(), for the std types that the builder treats specially. The name must be used
+// as is, as the prelude or a `use` brings it in, or through the module of std, core or alloc that
+// defines it: `std::option::Option<T>` and `::std::collections::HashMap<K, V>` are recognised,
+// `other::Option<T>` is some other type.
+fn std_type(ty: &Type) -> Option<(&Ident, Vec<&Type>)> {
+    let syn::Type::Path(TypePath { path, .. }) = ty else {
+        return None;
+    };
+    let (ident, args) = generic_type(ty)?;
+    let modules = path
+        .segments
+        .iter()
+        .take(path.segments.len() - 1)
+        .map(|segment| {
+            segment
+                .arguments
+                .is_empty()
+                .then(|| segment.ident.to_string())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let Some((krate, module)) = modules.split_first() else {
+        // `::Option<T>` names a crate
+        return path.leading_colon.is_none().then_some((ident, args));
+    };
+    let module = module.join("::");
+    let name = ident.to_string();
+    let known_module = match name.as_str() {
+        "Option" => module == "option",
+        "Vec" => module == "vec",
+        _ => {
+            let submodule = match name.as_str() {
+                "HashMap" => "hash_map",
+                "HashSet" => "hash_set",
+                "BTreeMap" => "btree_map",
+                "BTreeSet" => "btree_set",
+                "VecDeque" => "vec_deque",
+                "LinkedList" => "linked_list",
+                "BinaryHeap" => "binary_heap",
+                _ => return None,
+            };
+            module == "collections" || module == format!("collections::{}", submodule)
+        }
+    };
+    (["std", "core", "alloc"].contains(&krate.as_str()) && known_module).then_some((ident, args))
 }
@@ -9,6 +9,7 @@ use derive_builder::Builder;
 //    Bar,
 //}
 
+#[allow(dead_code)]
 #[derive(Builder)]
 pub struct Command {
     executable: String,
@@ -0,0 +1,135 @@
+// `#[builder(sub_builder)]`: a field whose type derives Builder too is stored as that type's
+// builder, which the builder hands out by reference in place of a setter. build() builds the
+// nested value from it, or from an empty builder if it was never asked for, so that a nested
+// struct with nothing required needs no mention at all. A nested field left unset is reported with
+// its full path, such as `server.port`, through the `uninitialized_field()` method of the
+// generated error of any builder but a `no_std` one; any other error of the nested builder, and
+// any error of a nested builder with `#[builder(error = ...)]` or `no_std`, is reported by its
+// message.
+//
+//     pub struct ConfigBuilder {
+//         server: Option<ServerBuilder>,
+//     }
+//
+//     impl ConfigBuilder {
+//         pub fn server(&mut self) -> &mut ServerBuilder { ... }
+//     }
+//
+// The builder type is `{Type}Builder` next to the field type, or the one given by
+// `sub_builder = "..."` for a builder renamed with `#[builder(name = "...")]`.
+//
+// A mutable build() builds the nested value from a clone of the nested builder, which must then be
+// Clone, such as through `#[builder(derive(Clone))]`, so that a build() failing on a later field
+// leaves the nested builder as it was.
+
+use crate::{error, BuilderField, BuilderInput};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Type, TypePath};
+
+// `path::Server<T>` -> `path::ServerBuilder<T>`
+pub(crate) fn builder_type(ty: &Type) -> Option<Type> {
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    let mut path = path.clone();
+    let last = path.segments.last_mut()?;
+    last.ident = format_ident!("{}Builder", last.ident);
+    Some(Type::Path(TypePath { qself: None, path }))
+}
+
+// the method handing out the nested builder, created on first use
+pub(crate) fn accessor(field: &BuilderField) -> TokenStream {
+    let (name, vis, ty, target) = (&field.name, &field.vis, &field.ty, field.target);
+    let docs = match (&field.opts.doc, field.docs.is_empty()) {
+        (Some(doc), _) => quote!(#[doc = #doc]),
+        (None, false) => {
+            let docs = &field.docs;
+            quote!(#(#docs)*)
+        }
+        (None, true) => {
+            let doc = format!("The builder of `{}`.", name.unraw());
+            quote!(#[doc = #doc])
+        }
+    };
+    quote! {
+        #docs
+        #vis fn #name(&mut self) -> &mut #ty {
+            self.#name.get_or_insert_with(<#target>::builder)
+        }
+    }
+}
+
+// The fallback of `uninitialized_field()`, declared in build() when some field has a sub-builder,
+// for the nested errors that lack the method: an inherent method takes precedence over a trait's.
+pub(crate) fn helper_trait(input: &BuilderInput) -> Option<TokenStream> {
+    if !input.has_sub_builder() {
+        return None;
+    }
+    Some(quote! {
+        trait __UninitializedField {
+            fn uninitialized_field(&self) -> ::core::option::Option<&'static str> {
+                ::core::option::Option::None
+            }
+        }
+
+        impl<T: ?::core::marker::Sized> __UninitializedField for T {}
+    })
+}
+
+// the value of `field` in build() from its stored builder, if any
+pub(crate) fn value(
+    input: &BuilderInput,
+    field: &BuilderField,
+    stored: TokenStream,
+) -> TokenStream {
+    let target = field.target;
+    let error_name = error::error_name(input);
+    let name_str = field.name.unraw().to_string();
+    let alloc = input.alloc_crate();
+    let err = input.build_error(quote! {
+        match err.uninitialized_field() {
+            ::core::option::Option::Some(path) => #error_name::UninitializedNestedField(
+                #alloc::format!("{}.{}", #name_str, path),
+            ),
+            ::core::option::Option::None => #error_name::SubBuilderError(
+                #name_str,
+                #alloc::string::ToString::to_string(&err),
+            ),
+        }
+    });
+    let build = |builder: TokenStream| {
+        quote! {
+            match { #builder }.build() {
+                ::core::result::Result::Ok(value) => value,
+                ::core::result::Result::Err(err) => return #err,
+            }
+        }
+    };
+    let built = build(quote!(builder));
+    let unset = match &field.default {
+        Some(default) => default.clone(),
+        None => build(quote!(<#target>::builder())),
+    };
+    quote! {
+        match #stored {
+            ::core::option::Option::Some(builder) => #built,
+            ::core::option::Option::None => #unset,
+        }
+    }
+}
+
+// merges a nested builder set in `other` into the one of `self`, rather than replacing it
+pub(crate) fn merge(field: &BuilderField) -> TokenStream {
+    let name = &field.name;
+    quote! {
+        if let ::core::option::Option::Some(value) = other.#name {
+            match &mut self.#name {
+                ::core::option::Option::Some(builder) => {
+                    builder.merge(value);
+                }
+                ::core::option::Option::None => self.#name = ::core::option::Option::Some(value),
+            }
+        }
+    }
+}
@@ -0,0 +1,340 @@
+// `#[builder(typestate)]`: instead of an `Option` that build() checks at runtime, every required
+// field is stored in a type parameter of the builder. The parameter is `()` while the field is
+// unset and `(T,)` once its setter has run, and build() is only implemented for the builder whose
+// parameters are all set, so forgetting a field is a compile error. build() then cannot fail and
+// returns the struct itself, unless a `build_fn(validate)` or `finalize_with` function may still
+// reject the builder. Setting a field again replaces its value, so that a builder made from an
+// existing value can be changed, and `clear_x()` takes the field back to `()`. The setters of a
+// `#[builder(const)]` builder require the states to be Copy, as a const fn cannot drop them.
+//
+//     pub struct CommandBuilder<__Executable = (), __Env = ()> {
+//         executable: __Executable,
+//         env: __Env,
+//         current_dir: Option<Option<String>>,
+//     }
+//
+//     impl<__Executable, __Env> CommandBuilder<__Executable, __Env> {
+//         fn executable(self, value: String) -> CommandBuilder<(String,), __Env> { ... }
+//     }
+//
+//     impl CommandBuilder<(String,), (Vec<String>,)> {
+//         pub fn build(self) -> Command { ... }
+//     }
+
+use crate::{
+    error, field_setters, finalize, phantom_type, setter_sigs, struct_default, validate_call,
+    BuilderInput, SetterSig, SetterValue,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, GenericParam, Ident};
+
+pub(crate) fn expand(input: &BuilderInput) -> TokenStream {
+    let name = input.name;
+    let builder_name = &input.builder_name;
+    let generics = input.generics;
+    let where_clause = &generics.where_clause;
+
+    // the struct's own parameters as declared, with bounds but without defaults, and as arguments
+    let decl_params = generics.params.iter();
+    let impl_params: Vec<_> = generics
+        .params
+        .iter()
+        .map(|param| {
+            let mut param = param.clone();
+            match &mut param {
+                GenericParam::Type(ty) => {
+                    ty.eq_token = None;
+                    ty.default = None;
+                }
+                GenericParam::Const(c) => {
+                    c.eq_token = None;
+                    c.default = None;
+                }
+                GenericParam::Lifetime(_) => {}
+            }
+            param
+        })
+        .collect();
+    let args: Vec<_> = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Type(ty) => {
+                let ident = &ty.ident;
+                quote!(#ident)
+            }
+            GenericParam::Lifetime(lt) => {
+                let lt = &lt.lifetime;
+                quote!(#lt)
+            }
+            GenericParam::Const(c) => {
+                let ident = &c.ident;
+                quote!(#ident)
+            }
+        })
+        .collect();
+
+    // one state parameter per required field, in field order
+    let states: Vec<Option<Ident>> = input
+        .fields
+        .iter()
+        .map(|field| {
+            field
+                .is_required()
+                .then(|| format_ident!("__{}", upper_camel_case(&field.name.unraw().to_string())))
+        })
+        .collect();
+    let state_params: Vec<_> = states.iter().flatten().collect();
+
+    let mut field_list = Vec::with_capacity(input.fields.len());
+    let mut builder_init_list = Vec::with_capacity(input.fields.len());
+    let mut optional_setters = Vec::new();
+    let mut required_setters = Vec::new();
+    let mut values = Vec::with_capacity(input.fields.len());
+    // the state arguments of the builder on which build() is available
+    let mut set_states = Vec::new();
+    let constness = input.constness();
+    let phantom_move = phantom_type(generics).map(|_| quote!(__phantom: self.__phantom,));
+
+    for (field, state) in input.fields.iter().zip(&states) {
+        let (name, ty) = (&field.name, &field.ty);
+        match (&field.kind, state) {
+            (_, Some(state)) => {
+                field_list.push(quote! {
+                    #name: #state,
+                });
+                builder_init_list.push(quote! {
+                    #name: (),
+                });
+                values.push(finalize::value(input, field, quote!(self.#name.0)));
+                set_states.push(quote!((#ty,)));
+
+                // every other field is moved over unchanged into the builder of the new state
+                let moved_with = |state_value: TokenStream| {
+                    let moved = input.fields.iter().map(|other| {
+                        let other = &other.name;
+                        if other == name {
+                            quote!(#other: #state_value,)
+                        } else {
+                            quote!(#other: self.#other,)
+                        }
+                    });
+                    quote! {
+                        #builder_name {
+                            #(#moved)*
+                            #phantom_move
+                        }
+                    }
+                };
+                // A const fn cannot drop values of a generic type, which the states of the other
+                // fields and the previous state of this one are. Copy types never need dropping.
+                let copy_bound = input
+                    .opts
+                    .constness
+                    .as_ref()
+                    .map(|_| quote!(: ::core::marker::Copy));
+                let builder_in = |field_state: TokenStream| {
+                    let states = state_params.iter().map(|s| {
+                        if *s == state {
+                            field_state.clone()
+                        } else {
+                            quote!(#s)
+                        }
+                    });
+                    quote!(#builder_name<#(#args,)* #(#states,)*>)
+                };
+                let moved = moved_with(quote!((value,)));
+                let to_builder = builder_in(quote!((#ty,)));
+                let setters = setter_sigs(field, name, &SetterValue::Value(quote!(#ty)))
+                    .into_iter()
+                    .map(|sig| {
+                        let SetterSig {
+                            name,
+                            generics,
+                            params,
+                            convert,
+                            error,
+                        } = sig;
+                        let (ret, result) = match error {
+                            Some(error) => (
+                                quote!(::core::result::Result<#to_builder, #error>),
+                                quote!(::core::result::Result::Ok(#moved)),
+                            ),
+                            None => (to_builder.clone(), moved.clone()),
+                        };
+                        let vis = &field.vis;
+                        let docs = field.setter_docs();
+                        quote! {
+                            #docs
+                            #vis #constness fn #name #generics (self, #params) -> #ret {
+                                #convert
+                                #result
+                            }
+                        }
+                    });
+                // clearing the field takes the builder back to its unset state
+                let clear = format_ident!("clear_{}", name.unraw());
+                let clear_doc = format!("Unsets `{}`, as if it had never been set.", name.unraw());
+                let cleared = moved_with(quote!(()));
+                let unset_builder = builder_in(quote!(()));
+                let vis = &field.vis;
+                required_setters.push(quote! {
+                    impl<#(#impl_params,)* #(#state_params #copy_bound,)*> #builder_name<#(#args,)* #(#state_params,)*> #where_clause {
+                        #(#setters)*
+
+                        #[doc = #clear_doc]
+                        #[allow(non_snake_case)]
+                        #vis #constness fn #clear(self) -> #unset_builder {
+                            #cleared
+                        }
+                    }
+                });
+            }
+            (_, None) => {
+                // fields without a state parameter are exactly the ones with an unset value
+                let unset = field.unset_value();
+                field_list.push(quote! {
+                    #name: ::core::option::Option<#ty>,
+                });
+                builder_init_list.push(quote! {
+                    #name: ::core::option::Option::None,
+                });
+                let value = finalize::value(input, field, quote!(value));
+                values.push(quote! {
+                    match self.#name {
+                        ::core::option::Option::Some(value) => #value,
+                        ::core::option::Option::None => #unset,
+                    }
+                });
+                optional_setters.extend(field_setters(input, field));
+            }
+        }
+    }
+
+    if let Some(phantom) = phantom_type(generics) {
+        field_list.push(quote! {
+            __phantom: #phantom,
+        });
+        builder_init_list.push(quote! {
+            __phantom: ::core::marker::PhantomData,
+        });
+    }
+    let unset_states = state_params.iter().map(|_| quote!(()));
+    // a builder made from an existing value has every field set, see the plain builder in lib.rs
+    let from_value = (input.variant.is_none() && !input.has_finalize()).then(|| {
+        let set_fields = input.fields.iter().zip(&states).map(|(field, state)| {
+            let (name, member) = (&field.name, &field.member);
+            match state {
+                Some(_) => quote!(#name: (value.#member,),),
+                None => quote!(#name: ::core::option::Option::Some(value.#member),),
+            }
+        });
+        let phantom = phantom_type(generics).map(|_| quote!(__phantom: ::core::marker::PhantomData,));
+        let set_builder = quote!(#builder_name<#(#args,)* #(#set_states,)*>);
+        let vis = &input.vis;
+        quote! {
+            impl<#(#impl_params,)*> ::core::convert::From<#name<#(#args,)*>> for #set_builder #where_clause {
+                fn from(value: #name<#(#args,)*>) -> Self {
+                    #builder_name {
+                        #(#set_fields)*
+                        #phantom
+                    }
+                }
+            }
+
+            impl<#(#impl_params,)*> #name<#(#args,)*> #where_clause {
+                /// A builder set to a copy of every field of `self`.
+                #vis fn to_builder(&self) -> #set_builder
+                where
+                    for<'__b> Self: ::core::clone::Clone,
+                {
+                    ::core::convert::From::from(::core::clone::Clone::clone(self))
+                }
+            }
+        }
+    });
+    let struct_default = struct_default(input);
+    let builder_fn = input.builder_fn();
+    let (bindings, construct) = input.construct(&values);
+    let vis = &input.vis;
+    let builder_attrs = input.builder_attrs();
+    let builder_fn_doc = input.builder_fn_doc();
+    let build_doc = input.build_doc();
+    let asyncness = input.asyncness();
+    let finalize_trait = finalize::helper_trait(input);
+    // nothing can go wrong in build() unless a validation or finalize function may reject the builder
+    let validate = validate_call(input, quote!(&self));
+    let (build_fn, error_def) = match validate.is_some() || input.has_finalize() {
+        true => {
+            let error_ty = error::build_error_type(input);
+            let build_fn = quote! {
+                #build_doc
+                #vis #constness #asyncness fn build(self) -> ::core::result::Result<#name<#(#args,)*>, #error_ty> {
+                    #finalize_trait
+                    #validate
+                    #struct_default
+                    #bindings
+                    ::core::result::Result::Ok(#construct)
+                }
+            };
+            (build_fn, Some(error::expand(input)))
+        }
+        false => (
+            quote! {
+                #build_doc
+                #vis #constness #asyncness fn build(self) -> #name<#(#args,)*> {
+                    #struct_default
+                    #bindings
+                    #construct
+                }
+            },
+            None,
+        ),
+    };
+
+    quote! {
+        impl<#(#impl_params,)*> #name<#(#args,)*> #where_clause {
+            #builder_fn_doc
+            #vis #constness fn #builder_fn() -> #builder_name<#(#args,)* #(#unset_states,)*> {
+                #builder_name {
+                    #(#builder_init_list)*
+                }
+            }
+        }
+
+        #builder_attrs
+        #vis struct #builder_name<#(#decl_params,)* #(#state_params = (),)*> #where_clause {
+            #(#field_list)*
+        }
+
+        impl<#(#impl_params,)* #(#state_params,)*> #builder_name<#(#args,)* #(#state_params,)*> #where_clause {
+            #(#optional_setters)*
+        }
+
+        #(#required_setters)*
+
+        impl<#(#impl_params,)*> #builder_name<#(#args,)* #(#set_states,)*> #where_clause {
+            #build_fn
+        }
+
+        #from_value
+
+        #error_def
+    }
+}
+
+// `current_dir` -> `CurrentDir`, so that the state parameters follow the type parameter naming
+// convention and do not trigger `non_camel_case_types` in the caller's crate
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
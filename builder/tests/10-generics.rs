@@ -0,0 +1,64 @@
+// Structs being built are often generic. The builder has to carry over every
+// lifetime, type and const parameter of the original struct together with
+// their bounds and the where-clause, so that the builder of `Request<'a, T,
+// N>` is `RequestBuilder<'a, T, N>` and `build()` produces exactly that
+// `Request`.
+//
+// Some parameters may not show up in any field the builder stores. Those still
+// have to be used somewhere or the generated struct is rejected, so the builder
+// keeps a PhantomData marker mentioning them.
+
+use derive_builder::Builder;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+pub trait Serialize {
+    fn serialize(&self) -> String;
+}
+
+impl Serialize for u32 {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+}
+
+#[derive(Builder)]
+pub struct Request<'a, T: Serialize, const N: usize>
+where
+    T: Clone + Debug,
+{
+    url: &'a str,
+    body: Option<T>,
+    #[builder(each = "header")]
+    headers: Vec<(&'a str, String)>,
+    retries: [u8; N],
+}
+
+#[derive(Builder)]
+pub struct Tagged<T, U = ()> {
+    id: u64,
+    marker: PhantomData<(T, U)>,
+}
+
+fn main() {
+    let url = String::from("https://example.com");
+    let request: Request<u32, 2> = Request::builder()
+        .url(&url)
+        .body(7)
+        .header(("accept", "text/plain".to_owned()))
+        .retries([1, 2])
+        .build()
+        .unwrap();
+
+    assert_eq!(request.url, "https://example.com");
+    assert_eq!(request.body.map(|body| body.serialize()).as_deref(), Some("7"));
+    assert_eq!(request.headers.len(), 1);
+    assert_eq!(request.retries, [1, 2]);
+
+    let tagged: Tagged<String> = Tagged::builder()
+        .id(1)
+        .marker(PhantomData)
+        .build()
+        .unwrap();
+    assert_eq!(tagged.id, 1);
+}
@@ -0,0 +1,47 @@
+// Large configuration structs make it easy to forget one of the required
+// fields, and the default builder only notices when build() runs.
+//
+// With #[builder(typestate)] on the struct, every required field is tracked in
+// a type parameter of the builder instead. Setting the field changes the type
+// of the builder, and build() only exists on the builder whose required fields
+// have all been set. Optional fields and `each` setters can be called in any
+// state. Because all the checking happens at compile time, build() returns the
+// struct directly rather than a Result.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Window<'a, T: Clone, const N: usize = 2> {
+    title: &'a str,
+    size: [T; N],
+}
+
+fn main() {
+    let command: Command = Command::builder()
+        .arg("build".to_owned())
+        .env(vec![])
+        .current_dir("..".to_owned())
+        .executable("cargo".to_owned())
+        .arg("--release".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.env.is_empty());
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    let window: Window<u32> = Window::builder().size([640, 480]).title("main").build();
+    assert_eq!(window.title, "main");
+    assert_eq!(window.size, [640, 480]);
+}
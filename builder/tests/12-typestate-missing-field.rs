@@ -0,0 +1,20 @@
+// In typestate mode a builder whose required fields have not all been set has
+// no build() method, so the mistake is reported by `cargo check`.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let _command = Command::builder()
+        .arg("build".to_owned())
+        .current_dir("..".to_owned())
+        .build();
+}
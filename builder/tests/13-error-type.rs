@@ -0,0 +1,61 @@
+// Rather than a boxed error built from a string, build() returns a dedicated
+// `{Name}BuilderError` enum generated next to the builder. Callers can match
+// on the variant to find out which required field was missing, and the error
+// implements Display, std::error::Error, Clone and PartialEq.
+//
+// Projects with their own error type can name it with
+// #[builder(error = Path)]. build() then returns that type, converting the
+// generated error through a From impl that the project provides.
+
+use derive_builder::Builder;
+use std::error::Error;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AppError {
+    MissingField(&'static str),
+    Other(String),
+}
+
+impl From<ConfigBuilderError> for AppError {
+    fn from(err: ConfigBuilderError) -> Self {
+        match err {
+            ConfigBuilderError::UninitializedField(field) => AppError::MissingField(field),
+            other => AppError::Other(other.to_string()),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(error = AppError)]
+pub struct Config {
+    name: String,
+    port: u16,
+}
+
+fn main() {
+    let err = Command::builder().arg("build".to_owned()).build().err().unwrap();
+    assert_eq!(err, CommandBuilderError::UninitializedField("executable"));
+    assert_eq!(err.clone(), err);
+    assert_eq!(err.to_string(), "`executable` is not set");
+
+    let boxed: Box<dyn Error> = Box::new(err);
+    assert_eq!(boxed.to_string(), "`executable` is not set");
+
+    let err = Config::builder().name("app".to_owned()).build().err().unwrap();
+    assert_eq!(err, AppError::MissingField("port"));
+
+    let config = Config::builder()
+        .name("app".to_owned())
+        .port(8080)
+        .build()
+        .unwrap();
+    assert_eq!(config.port, 8080);
+}
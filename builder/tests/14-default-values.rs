@@ -0,0 +1,89 @@
+// Adding a field to a struct should not break every place that builds it.
+//
+// A field marked #[builder(default)] falls back to Default::default() when its
+// setter is never called, and #[builder(default = "...")] falls back to the
+// given expression instead. The same attribute on the struct makes every field
+// without a default of its own fall back to the corresponding field of the
+// struct's Default impl or of the given expression.
+//
+// Fields with a default are optional in typestate mode as well.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(default)]
+    env: Vec<String>,
+    #[builder(default = r#"Some("/".to_owned())"#)]
+    current_dir: Option<String>,
+    #[builder(default = "3 * 10")]
+    timeout: u32,
+}
+
+#[derive(Builder)]
+#[builder(default)]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(default = "8")]
+    workers: usize,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server {
+            host: "localhost".to_owned(),
+            port: 80,
+            workers: 1,
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(default = "Limits { min: 1, max: 10 }")]
+pub struct Limits {
+    min: u32,
+    max: u32,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    #[builder(default = "5")]
+    retries: u8,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.args.is_empty());
+    assert!(command.env.is_empty());
+    assert_eq!(command.current_dir.as_deref(), Some("/"));
+    assert_eq!(command.timeout, 30);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .timeout(5)
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.timeout, 5);
+
+    let server = Server::builder().port(8080).build().unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.workers, 8);
+
+    let limits = Limits::builder().max(20).build().unwrap();
+    assert_eq!((limits.min, limits.max), (1, 20));
+
+    let job = Job::builder().name("backup".to_owned()).build();
+    assert_eq!(job.retries, 5);
+}
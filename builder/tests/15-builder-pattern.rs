@@ -0,0 +1,89 @@
+// By default setters take `&mut self` and build() moves the values out of the
+// builder. #[builder(pattern = "...")] selects a different way of passing the
+// builder around:
+//
+//   - "owned": setters take and return the builder by value and build()
+//     consumes it, so no field type has to implement Clone.
+//
+//   - "mutable": the default described above. A build() that fails leaves the
+//     builder as it was, so the missing fields can be set and build() retried.
+//
+//   - "immutable": setters take `&self` and return an updated copy and build()
+//     clones the values, so one partially filled builder can serve as a
+//     template for many structs.
+
+use derive_builder::Builder;
+
+// Deliberately not Clone.
+#[derive(Debug, PartialEq)]
+pub struct Handle(u32);
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Connection {
+    handle: Handle,
+    #[builder(each = "tag")]
+    tags: Vec<Handle>,
+    fallback: Option<Handle>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "mutable")]
+pub struct Command {
+    executable: String,
+    current_dir: Option<Handle>,
+}
+
+#[derive(Builder, Debug)]
+pub struct Pair {
+    first: String,
+    second: String,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Request {
+    method: String,
+    url: String,
+    #[builder(each = "header")]
+    headers: Vec<String>,
+}
+
+fn main() {
+    let builder = Connection::builder().handle(Handle(1)).tag(Handle(2));
+    let connection = builder.fallback(Handle(3)).build().unwrap();
+    assert_eq!(connection.handle, Handle(1));
+    assert_eq!(connection.tags, vec![Handle(2)]);
+    assert_eq!(connection.fallback, Some(Handle(3)));
+
+    let mut builder = Command::builder();
+    builder.executable("cargo".to_owned()).current_dir(Handle(4));
+    let command = builder.build().unwrap();
+    assert_eq!(command.current_dir, Some(Handle(4)));
+    assert!(builder.build().is_err());
+
+    let mut builder = Pair::builder();
+    builder.first("a".to_owned());
+    assert_eq!(
+        builder.build().unwrap_err(),
+        PairBuilderError::UninitializedField("second")
+    );
+    builder.second("b".to_owned());
+    let pair = builder.build().unwrap();
+    assert_eq!((pair.first, pair.second), ("a".to_owned(), "b".to_owned()));
+
+    let template = Request::builder()
+        .method("GET".to_owned())
+        .header("accept: */*".to_owned());
+    let first = template.url("/first".to_owned()).build().unwrap();
+    let second = template
+        .url("/second".to_owned())
+        .header("x-retry: 1".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(first.url, "/first");
+    assert_eq!(first.headers.len(), 1);
+    assert_eq!(second.method, "GET");
+    assert_eq!(second.headers.len(), 2);
+    assert!(template.build().is_err());
+}
@@ -0,0 +1,73 @@
+// Setters take exactly the type of the field by default, which makes call
+// sites like `.executable("cargo".to_owned())` noisy.
+//
+// With #[builder(setter(into))] the setter is generic over anything that
+// converts into the field type. With #[builder(try_setter)] a fallible
+// `try_`-prefixed setter is generated as well, which goes through TryInto and
+// hands the conversion error back to the caller.
+//
+// The setter of an Option<T> field takes a T and wraps it in Some, which can
+// be spelled out as #[builder(setter(strip_option))]. Use
+// #[builder(setter(option))] for a setter that takes the Option<T> itself, for
+// example to pass along a value that may be missing.
+
+use derive_builder::Builder;
+use std::num::TryFromIntError;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(setter(into))]
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(setter(into, strip_option))]
+    current_dir: Option<String>,
+    #[builder(setter(option))]
+    stdin: Option<String>,
+    #[builder(try_setter, setter(into))]
+    nice: i8,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    #[builder(setter(into))]
+    name: String,
+    #[builder(try_setter)]
+    priority: u8,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .arg(String::from("--release"))
+        .current_dir("..")
+        .stdin(None)
+        .nice(-5i8)
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.stdin, None);
+    assert_eq!(command.nice, -5);
+
+    let mut builder = Command::builder();
+    builder.executable("cargo").stdin(Some("input".to_owned()));
+    let result: Result<_, TryFromIntError> = builder.try_nice(1000);
+    assert!(result.is_err());
+    builder.try_nice(10i64).unwrap();
+    let command = builder.build().unwrap();
+    assert_eq!(command.stdin.as_deref(), Some("input"));
+    assert_eq!(command.nice, 10);
+
+    let job = Job::builder()
+        .name("backup")
+        .try_priority(3u64)
+        .unwrap()
+        .build();
+    assert_eq!(job.name, "backup");
+    assert_eq!(job.priority, 3);
+    assert!(Job::builder().name("restore").try_priority(-1).is_err());
+}
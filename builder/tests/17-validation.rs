@@ -0,0 +1,72 @@
+// Some invariants span several fields and cannot be expressed by the type of
+// any single one of them.
+//
+// #[builder(build_fn(validate = "path::to::fn"))] names a function that takes
+// the builder by reference and returns Result<(), E> for any E implementing
+// Display. build() calls it before constructing the struct, and an Err is
+// returned from build() as the ValidationError variant of the builder error,
+// carrying the message of the validation error.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct Range {
+    start: u32,
+    end: u32,
+    step: Option<u32>,
+}
+
+impl RangeBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if start > end => {
+                Err(format!("start {} is after end {}", start, end))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+mod checks {
+    use super::JobBuilder;
+
+    pub fn has_name<S>(builder: &JobBuilder<S>) -> Result<(), &'static str> {
+        let _ = builder;
+        Ok(())
+    }
+
+    pub fn never(_: &super::ServerBuilder<(u16,)>) -> Result<(), std::fmt::Error> {
+        Err(std::fmt::Error)
+    }
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", build_fn(validate = "checks::has_name"))]
+pub struct Job<S> {
+    name: S,
+}
+
+#[derive(Builder)]
+#[builder(typestate, build_fn(validate = "checks::never"))]
+pub struct Server {
+    port: u16,
+}
+
+fn main() {
+    let range = Range::builder().start(1).end(10).build().unwrap();
+    assert_eq!((range.start, range.end, range.step), (1, 10, None));
+
+    let err = Range::builder().start(10).end(1).build().err().unwrap();
+    assert_eq!(
+        err,
+        RangeBuilderError::ValidationError("start 10 is after end 1".to_owned()),
+    );
+    assert_eq!(err.to_string(), "start 10 is after end 1");
+
+    let job = Job::builder().name("backup").build().unwrap();
+    assert_eq!(job.name, "backup");
+
+    let err = Server::builder().port(80).build().err().unwrap();
+    assert!(matches!(err, ServerBuilderError::ValidationError(_)));
+}
@@ -0,0 +1,84 @@
+// Configuration is often an enum whose variants carry named fields.
+//
+// Deriving Builder on an enum generates one builder per variant with fields.
+// For a variant `Source::Git { .. }` the builder is `SourceGitBuilder`,
+// created by `Source::git_builder()`, and its build() returns a `Source`. Unit
+// variants have nothing to build and get no builder.
+//
+// Builder attributes on the enum apply to the builders of all variants and can
+// be extended by attributes on a single variant. Field attributes work exactly
+// as they do on structs.
+
+use derive_builder::Builder;
+
+#[derive(Debug, PartialEq, Builder)]
+#[builder(pattern = "owned")]
+pub enum Source<'a> {
+    Git {
+        #[builder(setter(into))]
+        url: String,
+        rev: Option<&'a str>,
+    },
+    #[builder(typestate)]
+    Path {
+        dir: String,
+        #[builder(each = "exclude")]
+        excludes: Vec<String>,
+    },
+    #[builder(build_fn(validate = "Source::check_registry"))]
+    CratesIo {
+        #[builder(default = r#""1.0".to_owned()"#)]
+        version: String,
+    },
+    Stdin,
+}
+
+impl Source<'_> {
+    fn check_registry(builder: &SourceCratesIoBuilder) -> Result<(), &'static str> {
+        match &builder.version {
+            Some(version) if version.is_empty() => Err("empty version"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn main() {
+    let git = Source::git_builder()
+        .url("https://github.com/dtolnay/proc-macro-workshop")
+        .rev("master")
+        .build()
+        .unwrap();
+    assert_eq!(
+        git,
+        Source::Git {
+            url: "https://github.com/dtolnay/proc-macro-workshop".to_owned(),
+            rev: Some("master"),
+        },
+    );
+
+    let err = Source::git_builder().build().err().unwrap();
+    assert_eq!(err, SourceGitBuilderError::UninitializedField("url"));
+
+    let path = Source::path_builder()
+        .exclude("target".to_owned())
+        .dir("..".to_owned())
+        .build();
+    assert_eq!(
+        path,
+        Source::Path {
+            dir: "..".to_owned(),
+            excludes: vec!["target".to_owned()],
+        },
+    );
+
+    let crates_io = Source::crates_io_builder().build().unwrap();
+    assert_eq!(crates_io, Source::CratesIo { version: "1.0".to_owned() });
+    let err = Source::crates_io_builder()
+        .version(String::new())
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "empty version");
+
+    let _ = Source::Stdin;
+}
@@ -0,0 +1,51 @@
+// Tuple structs take part in builder-based construction as well. Their
+// setters are named after the position of the field, `_0`, `_1` and so on,
+// unless #[builder(name = "...")] gives the field a name. The same goes for
+// tuple variants of an enum.
+//
+// A unit struct has nothing to set, but still gets a builder so that generic
+// construction helpers can treat it like any other struct.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Rgb(
+    #[builder(name = "red")] u8,
+    #[builder(name = "green")] u8,
+    #[builder(name = "blue", default)] u8,
+);
+
+#[derive(Builder)]
+pub struct Meters(#[builder(setter(into))] f64);
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Pair<T>(T, Option<T>);
+
+#[derive(Builder)]
+pub struct Marker;
+
+#[derive(Debug, PartialEq, Builder)]
+pub enum Shape {
+    Circle(f64),
+    Rect { w: f64, h: f64 },
+}
+
+fn main() {
+    let color = Rgb::builder().red(255).green(128).build().unwrap();
+    assert_eq!((color.0, color.1, color.2), (255, 128, 0));
+
+    let err = Rgb::builder().red(1).build().err().unwrap();
+    assert_eq!(err, RgbBuilderError::UninitializedField("green"));
+
+    let distance = Meters::builder()._0(3.5f32).build().unwrap();
+    assert_eq!(distance.0, 3.5);
+
+    let pair = Pair::builder()._0("a")._1("b").build();
+    assert_eq!((pair.0, pair.1), ("a", Some("b")));
+
+    let Marker = Marker::builder().build().unwrap();
+
+    let circle = Shape::circle_builder()._0(1.0).build().unwrap();
+    assert_eq!(circle, Shape::Circle(1.0));
+}
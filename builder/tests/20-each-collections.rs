@@ -0,0 +1,62 @@
+// The one-element-at-a-time setter is not limited to Vec. Any collection
+// implementing Extend and Default works: VecDeque, HashSet, BTreeSet and the
+// other std collections take one element per call, while the setter of a
+// HashMap or BTreeMap takes the key and the value of one entry.
+//
+// For collections whose element type cannot be read off the field type, such
+// as a custom collection or a type alias, spell it out with
+//
+//     #[builder(each(name = "header", ty = "(String, String)"))]
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+#[derive(Default)]
+pub struct Headers(Vec<(String, String)>);
+
+impl Extend<(String, String)> for Headers {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+#[derive(Builder)]
+pub struct Request {
+    #[builder(each = "query")]
+    query: HashMap<String, String>,
+    #[builder(each = "cookie", setter(into))]
+    cookies: BTreeMap<String, String>,
+    #[builder(each = "flag")]
+    flags: HashSet<u8>,
+    #[builder(each = "step")]
+    steps: VecDeque<u32>,
+    #[builder(each(name = "header", ty = "(String, String)"))]
+    headers: Headers,
+}
+
+fn main() {
+    let request = Request::builder()
+        .query("q".to_owned(), "rust".to_owned())
+        .query("page".to_owned(), "2".to_owned())
+        .cookie("session", "abc")
+        .flag(1)
+        .flag(1)
+        .flag(2)
+        .step(1)
+        .step(2)
+        .header(("Accept".to_owned(), "*/*".to_owned()))
+        .build()
+        .unwrap();
+
+    assert_eq!(request.query.len(), 2);
+    assert_eq!(request.query["q"], "rust");
+    assert_eq!(request.cookies["session"], "abc");
+    assert_eq!(request.flags.len(), 2);
+    assert_eq!(request.steps, VecDeque::from(vec![1, 2]));
+    assert_eq!(request.headers.0, vec![("Accept".to_owned(), "*/*".to_owned())]);
+
+    // every collection starts out empty
+    let request = Request::builder().build().unwrap();
+    assert!(request.query.is_empty());
+    assert!(request.headers.0.is_empty());
+}
@@ -0,0 +1,107 @@
+// Option and the collections are recognised by their last path segment, so
+// fully qualified paths such as std::option::Option<T> or
+// ::std::vec::Vec<T> get the same treatment as the names brought in by the
+// prelude, down to the module of each collection such as
+// std::collections::btree_map::BTreeMap<K, V>. A path through any other
+// module names some other type: a `shapes::Option<T>` below is an ordinary
+// required field.
+//
+// A type alias hides what it stands for, so the derive cannot tell that
+// `Maybe<T>` is an Option. #[builder(optional)] says so explicitly, and
+// #[builder(required)] turns an Option field into a required one whose setter
+// takes the Option itself.
+
+use derive_builder::Builder;
+
+mod shapes {
+    #[derive(Debug, PartialEq)]
+    pub struct Option<T>(pub T);
+}
+
+type Maybe<T> = Option<T>;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: ::std::vec::Vec<String>,
+    #[builder(each = "var")]
+    vars: std::collections::BTreeMap<String, String>,
+    #[builder(each = "feature")]
+    features: std::collections::btree_set::BTreeSet<String>,
+    #[builder(each = "alias")]
+    aliases: std::collections::btree_map::BTreeMap<String, String>,
+    #[builder(each = "target")]
+    targets: std::collections::hash_set::HashSet<String>,
+    #[builder(each = "profile")]
+    profiles: std::collections::hash_map::HashMap<String, u8>,
+    #[builder(each = "job")]
+    jobs: std::collections::vec_deque::VecDeque<u32>,
+    #[builder(each = "step")]
+    steps: std::collections::linked_list::LinkedList<u32>,
+    #[builder(each = "priority")]
+    priorities: std::collections::binary_heap::BinaryHeap<u32>,
+    current_dir: std::option::Option<String>,
+    shape: shapes::Option<u8>,
+    #[builder(optional)]
+    nice: Maybe<i32>,
+    #[builder(required)]
+    timeout: Option<u64>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .var("RUST_LOG".to_owned(), "debug".to_owned())
+        .feature("serde".to_owned())
+        .alias("b".to_owned(), "build".to_owned())
+        .target("x86_64".to_owned())
+        .profile("release".to_owned(), 3)
+        .job(1)
+        .step(2)
+        .priority(3)
+        .shape(shapes::Option(1))
+        .timeout(None)
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.vars["RUST_LOG"], "debug");
+    assert!(command.features.contains("serde"));
+    assert_eq!(command.aliases["b"], "build");
+    assert!(command.targets.contains("x86_64"));
+    assert_eq!(command.profiles["release"], 3);
+    assert_eq!(command.jobs.front(), Some(&1));
+    assert_eq!(command.steps.front(), Some(&2));
+    assert_eq!(command.priorities.peek(), Some(&3));
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.shape, shapes::Option(1));
+    assert_eq!(command.nice, None);
+    assert_eq!(command.timeout, None);
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .shape(shapes::Option(2))
+        .nice(10)
+        .timeout(Some(30))
+        .build()
+        .unwrap();
+
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.nice, Some(10));
+    assert_eq!(command.timeout, Some(30));
+
+    // neither the foreign Option nor the required one may be left out
+    assert!(Command::builder()
+        .executable("cargo".to_owned())
+        .timeout(None)
+        .build()
+        .is_err());
+    assert!(Command::builder()
+        .executable("cargo".to_owned())
+        .shape(shapes::Option(3))
+        .build()
+        .is_err());
+}
@@ -0,0 +1,63 @@
+// Every key inside #[builder(...)] is checked, wherever it appears and however
+// many keys the attribute holds. Keys given twice, keys that conflict with
+// each other and misspelled keys are reported at the offending token, with a
+// suggestion when the key looks like a typo of a known one. An `each` setter
+// on a field that is not a collection is an error as well, rather than
+// generated code that fails to compile somewhere inside the expansion, and so
+// is a setter name that is not an identifier.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Duplicate {
+    #[builder(default, setter(into), default)]
+    executable: String,
+}
+
+#[derive(Builder)]
+pub struct Misspelled {
+    #[builder(setter(into, strip_opton))]
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(patern = "owned")]
+pub struct MisspelledStruct {
+    executable: String,
+}
+
+#[derive(Builder)]
+pub struct Conflicting {
+    #[builder(optional, required)]
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+pub struct NotACollection {
+    #[builder(each = "arg")]
+    args: String,
+}
+
+#[derive(Builder)]
+pub struct InvalidEachName {
+    #[builder(each = "my-arg")]
+    args: Vec<String>,
+}
+
+#[derive(Builder)]
+pub struct InvalidEachListName {
+    #[builder(each(name = "1x"))]
+    env: Vec<String>,
+}
+
+async fn connect(url: String) -> String {
+    url
+}
+
+#[derive(Builder)]
+pub struct AsyncFinalizeInSyncBuild {
+    #[builder(finalize_with(path = "connect", async))]
+    pool: String,
+}
+
+fn main() {}
@@ -0,0 +1,58 @@
+// The builder is declared with the visibility of the struct it builds, and
+// each setter with the visibility of its field, so a private struct does not
+// leak a public builder and private fields stay settable only from inside
+// their module.
+//
+// At the struct level, #[builder(name = "...")] renames the builder,
+// #[builder(vis = "...")] overrides its visibility and
+// #[builder(derive(...))] derives traits for it. Any other attribute can be
+// forwarded onto the builder with #[builder(struct_attr(...))].
+
+mod command {
+    use derive_builder::Builder;
+
+    #[derive(Builder, Debug)]
+    #[builder(
+        name = "CmdOpts",
+        vis = "pub(crate)",
+        derive(Debug, Clone, PartialEq),
+        struct_attr(derive(Default))
+    )]
+    pub struct Command {
+        pub executable: String,
+        #[builder(default)]
+        retries: u32,
+    }
+
+    impl Command {
+        pub fn retries(&self) -> u32 {
+            self.retries
+        }
+    }
+
+    impl CmdOpts {
+        pub fn retry(&mut self) -> &mut Self {
+            self.retries(3)
+        }
+    }
+}
+
+use command::CmdOpts;
+
+fn main() {
+    let mut opts = CmdOpts::default();
+    opts.executable("cargo".to_owned());
+    let snapshot = opts.clone();
+    assert_eq!(opts, snapshot);
+    assert!(format!("{:?}", opts).starts_with("CmdOpts"));
+
+    let command = opts.retry().build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.retries(), 3);
+
+    let command = command::Command::builder()
+        .executable("rustc".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.retries(), 0);
+}
@@ -0,0 +1,49 @@
+// Fields such as caches, ids or values derived from other fields are not
+// meant to be set through the builder. #[builder(skip)] leaves out their
+// setter, and build() initialises them with Default::default() or with the
+// expression given by #[builder(skip, default = "...")].
+//
+// The expression is evaluated after every other field has been built and can
+// refer to those built values as `self.field`.
+
+#![deny(non_snake_case)]
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(skip)]
+    cache: HashMap<String, String>,
+    #[builder(skip, default = "self.args.len()")]
+    argc: usize,
+    #[builder(skip, default = "format!(\"{} {}\", self.executable, self.args.join(\" \"))")]
+    line: String,
+    #[builder(skip, default = "self.args.is_empty()")]
+    _no_args: bool,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Span(u32, u32, #[builder(skip, default = "self.1 - self.0")] u32);
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+
+    assert!(command.cache.is_empty());
+    assert_eq!(command.argc, 2);
+    assert_eq!(command.line, "cargo build --release");
+    assert_eq!(command.args.len(), 2);
+    assert!(!command._no_args);
+
+    let span = Span::builder()._0(3)._1(10).build();
+    assert_eq!(span.2, 7);
+}
@@ -0,0 +1,43 @@
+// With the `serde` feature of derive_builder, #[builder(serde)] makes the
+// builder implement serde's Deserialize. Every field of the builder is
+// optional, so a partial configuration deserializes into a builder that can
+// be completed with setters. merge() layers one builder over another, taking
+// every field that is set in the later one.
+//
+// This test only runs with `cargo test --features serde`.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(serde)]
+pub struct Config {
+    host: String,
+    port: u16,
+    #[builder(name = "worker_count")]
+    workers: usize,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    log_file: Option<String>,
+}
+
+fn main() {
+    let defaults: ConfigBuilder = serde_json::from_str(
+        r#"{ "host": "localhost", "port": 8080, "workers": 4, "tags": ["base"] }"#,
+    )
+    .unwrap();
+    let file: ConfigBuilder = serde_json::from_str(r#"{ "port": 9090 }"#).unwrap();
+
+    let mut builder = defaults;
+    builder.merge(file);
+    builder.log_file("out.log".to_owned());
+    let config = builder.build().unwrap();
+
+    assert_eq!(config.host, "localhost");
+    assert_eq!(config.port, 9090);
+    assert_eq!(config.workers, 4);
+    assert_eq!(config.tags, vec!["base"]);
+    assert_eq!(config.log_file.as_deref(), Some("out.log"));
+
+    let mut empty: ConfigBuilder = serde_json::from_str("{}").unwrap();
+    assert!(empty.build().is_err());
+}
@@ -0,0 +1,66 @@
+// A builder can start from an existing value: XBuilder::from(x) takes the
+// value apart, and x.to_builder() does the same with a copy when the struct is
+// Clone. Every field of such a builder is set, so changing a few of them and
+// calling build() gives a modified copy.
+//
+// merge() takes over every field that is set in another builder, which
+// combines two partial builders into one.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+pub struct Request {
+    url: String,
+    method: String,
+    #[builder(each = "header")]
+    headers: Vec<String>,
+    body: Option<String>,
+}
+
+#[derive(Builder, Clone, Debug, PartialEq)]
+#[builder(typestate)]
+pub struct Point {
+    x: i32,
+    y: i32,
+    label: Option<String>,
+}
+
+// not Clone: only From is available
+#[derive(Builder)]
+pub struct Handle {
+    id: u32,
+}
+
+fn main() {
+    let request = Request::builder()
+        .url("https://example.com".to_owned())
+        .method("GET".to_owned())
+        .header("Accept: */*".to_owned())
+        .build()
+        .unwrap();
+
+    let copy = request
+        .to_builder()
+        .url("https://example.org".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(copy.url, "https://example.org");
+    assert_eq!(copy.method, request.method);
+    assert_eq!(copy.headers, request.headers);
+
+    let mut post = RequestBuilder::from(request.clone());
+    let mut overrides = Request::builder();
+    overrides.method("POST".to_owned()).body("{}".to_owned());
+    let post = post.merge(overrides).build().unwrap();
+    assert_eq!(post.url, request.url);
+    assert_eq!(post.method, "POST");
+    assert_eq!(post.body.as_deref(), Some("{}"));
+
+    let point = Point::builder().x(1).y(2).build();
+    let moved = point.to_builder().x(5).build();
+    assert_eq!(moved, Point { x: 5, ..point.clone() });
+    assert_eq!(PointBuilder::from(point.clone()).build(), point);
+
+    let handle = HandleBuilder::from(Handle { id: 7 }).build().unwrap();
+    assert_eq!(handle.id, 7);
+}
@@ -0,0 +1,60 @@
+// #[builder(const)] makes builder(), the setters and build() const fns, so
+// that `const` and `static` values can be defined through the builder. Such a
+// builder is always owned.
+//
+// Only what can be evaluated at compile time is allowed: the setters store
+// their argument as is and defaults are given as const expressions. Fields
+// whose types need dropping, such as String, cannot be overwritten in a const
+// context, so const builders are best suited to plain data. Combined with
+// typestate, the required fields must even be Copy.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const)]
+pub struct Uart {
+    baud: u32,
+    #[builder(default = "8")]
+    data_bits: u8,
+    parity: Option<bool>,
+    name: &'static str,
+}
+
+const CONSOLE: Uart = match Uart::builder()
+    .baud(115_200)
+    .name("console")
+    .parity(false)
+    .build()
+{
+    Ok(uart) => uart,
+    Err(_) => panic!("incomplete uart configuration"),
+};
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const, typestate)]
+pub struct Pin {
+    port: char,
+    number: u8,
+    #[builder(setter(option))]
+    pull_up: Option<bool>,
+}
+
+static LED: Pin = Pin::builder().port('B').number(7).build();
+
+fn main() {
+    assert_eq!(
+        CONSOLE,
+        Uart {
+            baud: 115_200,
+            data_bits: 8,
+            parity: Some(false),
+            name: "console",
+        }
+    );
+    assert_eq!(LED.pull_up, None);
+    assert_eq!(LED.number, 7);
+
+    // still usable at runtime
+    let error = Uart::builder().baud(9600).build().err().unwrap();
+    assert_eq!(error, UartBuilderError::UninitializedField("name"));
+}
@@ -0,0 +1,44 @@
+// A const builder cannot call trait methods, so options that go through
+// Extend, Into or Default are rejected at the attribute instead of producing
+// a const fn that fails to compile inside the expansion.
+//
+// A const fn cannot drop a String either, which the setters would when
+// overwriting one, so fields whose types need dropping are reported at the
+// field.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Pins {
+    #[builder(each = "pin")]
+    pins: Vec<u8>,
+}
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Clock {
+    #[builder(default)]
+    hz: u32,
+}
+
+#[derive(Builder)]
+#[builder(const, pattern = "mutable")]
+pub struct Timer {
+    period: u32,
+}
+
+#[derive(Builder)]
+#[builder(const, build_fn(async))]
+pub struct Uart {
+    baud: u32,
+}
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Label {
+    id: u32,
+    text: String,
+}
+
+fn main() {}
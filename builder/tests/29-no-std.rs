@@ -0,0 +1,81 @@
+// Firmware crates have no std. Under #[builder(no_std)] the generated code
+// only refers to ::core, and the generated error implements Display but not
+// std::error::Error.
+//
+// The ValidationError variant holds a String, so it only exists when the
+// builder has a build_fn(validate) function, which then needs the alloc crate
+// to be declared with `extern crate alloc;`, as do `finalize_with` and
+// `sub_builder` fields. A nested `no_std` builder has no
+// `uninitialized_field()`, so its missing fields are reported as a
+// SubBuilderError with the nested message.
+//
+// The test itself is a no_std crate, so that anything the expansion takes
+// from std fails to resolve. std is only linked, under another name, for the
+// runtime that calls main.
+
+#![no_std]
+
+extern crate alloc;
+extern crate std as host;
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(no_std)]
+pub struct Timer {
+    prescaler: u16,
+    #[builder(default = "0xffff")]
+    reload: u16,
+}
+
+#[derive(Builder, Debug)]
+#[builder(no_std, build_fn(validate = "Self::validate"))]
+pub struct Spi {
+    frequency: u32,
+}
+
+impl SpiBuilder {
+    fn validate(&self) -> Result<(), &'static str> {
+        match self.frequency {
+            Some(frequency) if frequency > 50_000_000 => Err("frequency out of range"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Builder, Debug)]
+#[builder(no_std, derive(Clone))]
+pub struct Pin {
+    number: u8,
+}
+
+#[derive(Builder, Debug)]
+#[builder(no_std)]
+pub struct Uart {
+    #[builder(sub_builder)]
+    tx: Pin,
+}
+
+fn main() {
+    let timer = Timer::builder().prescaler(8).build().unwrap();
+    assert_eq!(timer.reload, 0xffff);
+
+    let error = Timer::builder().build().unwrap_err();
+    assert_eq!(error, TimerBuilderError::UninitializedField("prescaler"));
+    assert_eq!(alloc::format!("{}", error), "`prescaler` is not set");
+
+    let error = Spi::builder().frequency(80_000_000).build().unwrap_err();
+    assert_eq!(
+        error,
+        SpiBuilderError::ValidationError("frequency out of range".into())
+    );
+
+    let error = Uart::builder().build().unwrap_err();
+    assert_eq!(
+        error,
+        UartBuilderError::SubBuilderError("tx", "`number` is not set".into())
+    );
+    let mut builder = Uart::builder();
+    builder.tx().number(3);
+    assert_eq!(builder.build().unwrap().tx.number, 3);
+}
@@ -0,0 +1,50 @@
+// The generated builder is documented, so that `missing_docs` holds for a
+// crate deriving Builder on its public structs.
+//
+// Setters carry the `///` comments of their field, including `each` setters,
+// or the text given by #[builder(doc = "...")]. The builder struct, builder()
+// and build() get a summary listing the required and the optional fields,
+// and #[builder(doc = "...")] on the struct replaces the one of the builder.
+
+//! A crate with documented public structs deriving Builder.
+
+#![deny(missing_docs)]
+
+use derive_builder::Builder;
+
+/// A command to run.
+#[derive(Builder)]
+pub struct Command {
+    /// The program to run.
+    pub executable: String,
+    /// Arguments passed to the program.
+    #[builder(each = "arg")]
+    pub args: Vec<String>,
+    /// Working directory.
+    #[builder(doc = "Runs the command in `dir` instead of the current directory.")]
+    pub current_dir: Option<String>,
+    #[allow(missing_docs)]
+    pub nice: Option<i32>,
+}
+
+/// A point on a grid.
+#[derive(Builder)]
+#[builder(typestate, doc = "Builds a [`Point`] one coordinate at a time.")]
+pub struct Point {
+    /// Column.
+    pub x: u32,
+    /// Row.
+    pub y: u32,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.args, ["build"]);
+
+    let point = Point::builder().x(1).y(2).build();
+    assert_eq!((point.x, point.y), (1, 2));
+}
@@ -0,0 +1,139 @@
+// Some fields can only be created when the struct is built, from a
+// configuration the builder holds until then: a connection pool from its URL,
+// a TLS config from the paths of its files.
+//
+// #[builder(finalize_with = "path")] on a field makes build() pass the value
+// that was set through the function to get the value of the field. With
+// #[builder(finalize_with(path = "...", ty = "..."))] the setter takes and the
+// builder stores `ty` rather than the field type. The function may return the
+// field type itself, or a Result whose error implements Display; an error is
+// returned from build() as the FinalizeError variant of the builder error,
+// naming the field. Unset fields get their default as usual, without going
+// through the function.
+//
+// #[builder(build_fn(async))] makes build() an async fn, which awaits the
+// finalize_with functions marked async, as in
+// #[builder(finalize_with(path = "...", async))], and calls the others as
+// they are.
+
+use derive_builder::Builder;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+#[derive(Debug, PartialEq)]
+pub struct Pool {
+    url: String,
+}
+
+async fn connect(url: String) -> Result<Pool, String> {
+    match url.strip_prefix("postgres://") {
+        Some(_) => Ok(Pool { url }),
+        None => Err(format!("unsupported url {}", url)),
+    }
+}
+
+fn trimmed(name: String) -> String {
+    name.trim().to_owned()
+}
+
+#[derive(Builder, Debug)]
+#[builder(build_fn(async))]
+pub struct Server {
+    #[builder(finalize_with = "trimmed")]
+    name: String,
+    #[builder(finalize_with(path = "connect", ty = "String", async), setter(into))]
+    pool: Pool,
+    #[builder(default = "8080")]
+    port: u16,
+}
+
+fn parse_port(port: String) -> Result<u16, std::num::ParseIntError> {
+    port.parse()
+}
+
+fn uppercase(host: Option<&str>) -> Option<String> {
+    host.map(str::to_uppercase)
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Listener {
+    #[builder(finalize_with(path = "parse_port", ty = "String"))]
+    port: u16,
+    #[builder(finalize_with(path = "uppercase", ty = "Option<&'static str>"))]
+    host: Option<String>,
+}
+
+// a minimal executor, the futures above never wait
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    let waker = Waker::from(Arc::new(Noop));
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            return output;
+        }
+    }
+}
+
+fn main() {
+    let server = block_on(
+        Server::builder()
+            .name(" api ".to_owned())
+            .pool("postgres://localhost")
+            .build(),
+    )
+    .unwrap();
+    assert_eq!(server.name, "api");
+    assert_eq!(server.pool.url, "postgres://localhost");
+    assert_eq!(server.port, 8080);
+
+    let err = block_on(
+        Server::builder()
+            .name("api".to_owned())
+            .pool("mysql://localhost")
+            .build(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ServerBuilderError::FinalizeError("pool", "unsupported url mysql://localhost".to_owned())
+    );
+    assert_eq!(
+        err.to_string(),
+        "`pool` could not be finalized: unsupported url mysql://localhost"
+    );
+
+    let err = block_on(Server::builder().name("api".to_owned()).build()).unwrap_err();
+    assert_eq!(err, ServerBuilderError::UninitializedField("pool"));
+
+    // the builder keeps the values of a failed build(), to be corrected and built again
+    let mut builder = Server::builder();
+    builder.name("api".to_owned()).pool("mysql://localhost");
+    assert!(block_on(builder.build()).is_err());
+    builder.pool("postgres://localhost");
+    let server = block_on(builder.build()).unwrap();
+    assert_eq!(server.name, "api");
+
+    let listener = Listener::builder()
+        .port("443".to_owned())
+        .host("localhost")
+        .build()
+        .unwrap();
+    assert_eq!(listener.port, 443);
+    assert_eq!(listener.host.as_deref(), Some("LOCALHOST"));
+
+    let listener = Listener::builder().port("80".to_owned()).build().unwrap();
+    assert_eq!(listener.host, None);
+
+    let err = Listener::builder().port("http".to_owned()).build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`port` could not be finalized: invalid digit found in string"
+    );
+}
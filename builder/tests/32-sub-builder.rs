@@ -0,0 +1,203 @@
+// A configuration is often a tree of structs, each deriving Builder. Instead of
+// building the inner structs separately and passing them in whole,
+// #[builder(sub_builder)] on a field stores the builder of its type and
+// replaces the setter with a method handing out that builder by reference, to
+// set the nested fields in place.
+//
+// build() builds the nested value from that builder, or from an empty one if
+// the field was never touched, so a nested struct without required fields
+// needs no mention. A nested field left unset is reported with its full path
+// as UninitializedNestedField, such as `server.port`, however deep the
+// nesting; any other error of the nested builder becomes a SubBuilderError
+// with its message, as is any error of a nested builder with its own
+// #[builder(error = ...)] type, which then has to implement Display. A field
+// `default` is used instead when the nested builder was never touched.
+//
+// The builder is taken to be `{Type}Builder`; a builder renamed with
+// #[builder(name = "...")] is given as #[builder(sub_builder = "...")].
+//
+// A failed build() leaves the nested builders as they were, even when it fails
+// on a later field after building them, so the default mutable build() builds
+// from clones of the nested builders, which derive Clone for it.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(derive(Clone))]
+pub struct Tls {
+    cert: String,
+    #[builder(default)]
+    verify: bool,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(build_fn(validate = "Self::validate"), derive(Clone))]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+impl ServerBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match self.port {
+            Some(0) => Err("port 0 is reserved".to_owned()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(name = "LogOptions", derive(Clone))]
+pub struct Logging {
+    #[builder(default = "\"info\".to_owned()")]
+    level: String,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Config {
+    name: String,
+    /// The server to listen with.
+    #[builder(sub_builder)]
+    server: Server,
+    #[builder(sub_builder = "LogOptions")]
+    logging: Logging,
+    #[builder(sub_builder, default = "Tls { cert: \"backup.pem\".to_owned(), verify: true }")]
+    backup: Tls,
+}
+
+#[derive(Debug)]
+pub struct CacheError(String);
+
+impl std::fmt::Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid cache: {}", self.0)
+    }
+}
+
+impl From<CacheBuilderError> for CacheError {
+    fn from(err: CacheBuilderError) -> Self {
+        CacheError(err.to_string())
+    }
+}
+
+#[derive(Builder, Debug)]
+#[builder(error = CacheError, derive(Clone))]
+pub struct Cache {
+    size: u32,
+}
+
+#[derive(Builder, Debug)]
+pub struct Service {
+    #[builder(sub_builder)]
+    cache: Cache,
+}
+
+fn replicas(count: String) -> Result<u32, std::num::ParseIntError> {
+    count.parse()
+}
+
+#[derive(Builder, Debug)]
+pub struct Deployment {
+    #[builder(sub_builder)]
+    tls: Tls,
+    #[builder(finalize_with(path = "replicas", ty = "String"))]
+    replicas: u32,
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    builder.name("app".to_owned());
+    builder.server().host("localhost".to_owned()).port(8080);
+    builder.server().tls().cert("cert.pem".to_owned());
+    let config = builder.build().unwrap();
+    assert_eq!(
+        config,
+        Config {
+            name: "app".to_owned(),
+            server: Server {
+                host: "localhost".to_owned(),
+                port: 8080,
+                tls: Tls {
+                    cert: "cert.pem".to_owned(),
+                    verify: false,
+                },
+            },
+            logging: Logging {
+                level: "info".to_owned(),
+            },
+            backup: Tls {
+                cert: "backup.pem".to_owned(),
+                verify: true,
+            },
+        }
+    );
+
+    let mut builder = Config::builder();
+    builder.name("app".to_owned());
+    builder.server().host("localhost".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err,
+        ConfigBuilderError::UninitializedNestedField("server.port".to_owned())
+    );
+    assert_eq!(err.to_string(), "`server.port` is not set");
+    assert_eq!(err.uninitialized_field().as_deref(), Some("server.port"));
+
+    let mut builder = Config::builder();
+    builder.name("app".to_owned());
+    builder.server().host("localhost".to_owned()).port(443);
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err,
+        ConfigBuilderError::UninitializedNestedField("server.tls.cert".to_owned())
+    );
+
+    let mut builder = Config::builder();
+    builder.name("app".to_owned());
+    builder.server().host("localhost".to_owned()).port(0);
+    builder.server().tls().cert("cert.pem".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`server` could not be built: port 0 is reserved"
+    );
+    assert_eq!(err.uninitialized_field(), None);
+
+    // a failed build() leaves the nested builders as they were
+    builder.server().port(8443);
+    let config = builder.build().unwrap();
+    assert_eq!(config.server.host, "localhost");
+    assert_eq!(config.server.tls.cert, "cert.pem");
+
+    let err = Service::builder().build().unwrap_err();
+    assert_eq!(
+        err,
+        ServiceBuilderError::SubBuilderError("cache", "invalid cache: `size` is not set".to_owned())
+    );
+    assert_eq!(err.uninitialized_field(), None);
+
+    // nor does one failing on a field after its nested builder was built
+    let mut builder = Deployment::builder();
+    builder.tls().cert("cert.pem".to_owned());
+    builder.replicas("many".to_owned());
+    assert_eq!(
+        builder.build().unwrap_err().to_string(),
+        "`replicas` could not be finalized: invalid digit found in string"
+    );
+    builder.replicas("3".to_owned());
+    let deployment = builder.build().unwrap();
+    assert_eq!(deployment.tls.cert, "cert.pem");
+    assert_eq!(deployment.replicas, 3);
+
+    // the nested builders of a later layer are merged into those of the first one
+    let mut base = ConfigBuilder::from(config);
+    let mut layer = Config::builder();
+    layer.server().port(9090);
+    base.merge(layer);
+    let config = base.build().unwrap();
+    assert_eq!(config.server.port, 9090);
+    assert_eq!(config.server.host, "localhost");
+    assert_eq!(config.server.tls.cert, "cert.pem");
+}
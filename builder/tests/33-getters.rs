@@ -0,0 +1,53 @@
+// The fields of the builder are private, so code holding a builder cannot tell
+// what has been set so far, for example to tell the user which command line
+// flags are still missing before calling build().
+//
+// #[builder(getters)] generates, for every field, `get_x()` returning the
+// value stored so far, if any, and `is_x_set()`. They are prefixed so that they
+// do not clash with the setters. `unset_fields()` lists the names of the
+// required fields that are not set yet, the ones build() would report as
+// missing.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(getters)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    #[builder(name = "timeout_secs")]
+    timeout: u64,
+}
+
+#[derive(Builder)]
+#[builder(getters, pattern = "owned")]
+pub struct Flags {
+    verbose: Option<bool>,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    assert_eq!(builder.get_executable(), None);
+    assert!(!builder.is_executable_set());
+    assert_eq!(builder.unset_fields(), ["executable", "timeout_secs"]);
+
+    builder.executable("cargo".to_owned()).arg("build".to_owned());
+    assert_eq!(builder.get_executable().map(String::as_str), Some("cargo"));
+    assert!(builder.is_executable_set());
+    assert_eq!(builder.get_args(), Some(&vec!["build".to_owned()]));
+    assert!(!builder.is_current_dir_set());
+    assert_eq!(builder.unset_fields(), ["timeout_secs"]);
+
+    builder.current_dir("/tmp".to_owned()).timeout_secs(30);
+    assert_eq!(builder.get_current_dir(), Some(&Some("/tmp".to_owned())));
+    assert_eq!(builder.get_timeout_secs(), Some(&30));
+    assert!(builder.unset_fields().is_empty());
+    assert!(builder.build().is_ok());
+
+    let flags = Flags::builder();
+    assert!(flags.unset_fields().is_empty());
+    let flags = flags.verbose(true);
+    assert!(flags.is_verbose_set());
+}
@@ -0,0 +1,116 @@
+// Once set, a field stays set, and a collection filled through its `each`
+// setter only grows one element at a time.
+//
+// Every field gets a `clear_x()` setter that unsets it, as if its setter had
+// never been called. Clearing a required field of a typestate builder takes
+// its state back to unset, so build() is unavailable again until it is set.
+//
+// An `each` field also gets a bulk setter adding every element of an
+// iterator, named after the field, or `extend_x` when the one-element setter
+// already has the field's name, and `set_x` replacing the whole collection.
+// With `setter(into)` the elements of the iterator are converted one by one.
+//
+// A field named `_x` gets `clear__x()` and `set__x()`, which are allowed
+// although they are not snake case, as are the getters of the next test.
+
+#![deny(non_snake_case)]
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(each = "env")]
+    env: HashMap<String, String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "immutable")]
+pub struct Features {
+    #[builder(each = "feature")]
+    features: Vec<&'static str>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Point {
+    x: i32,
+    y: i32,
+    label: Option<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(getters)]
+pub struct Private {
+    _id: u32,
+    #[builder(each = "tag")]
+    _tags: Vec<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Secret {
+    _key: u32,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder
+        .executable("cargo".to_owned())
+        .arg("build")
+        .args(["--release", "--locked"])
+        .env("RUST_LOG".to_owned(), "debug".to_owned())
+        .extend_env([("CI".to_owned(), "1".to_owned())])
+        .current_dir("/tmp".to_owned());
+    builder.clear_current_dir();
+    let command = builder.build().unwrap();
+    assert_eq!(command.args, ["build", "--release", "--locked"]);
+    assert_eq!(command.env.len(), 2);
+    assert_eq!(command.current_dir, None);
+
+    let mut builder = Command::builder();
+    builder
+        .executable("cargo".to_owned())
+        .arg("build")
+        .set_args(["test"])
+        .env("CI".to_owned(), "1".to_owned())
+        .set_env(HashMap::new());
+    let command = builder.build().unwrap();
+    assert_eq!(command.args, ["test"]);
+    assert!(command.env.is_empty());
+
+    let mut builder = Command::builder();
+    builder.executable("cargo".to_owned()).clear_executable();
+    assert!(builder.build().is_err());
+
+    let base = Features::builder().features(["std", "serde"]);
+    let without = base.clear_features();
+    assert_eq!(base.build().unwrap().features, ["std", "serde"]);
+    assert!(without.build().unwrap().features.is_empty());
+
+    let point = Point::builder()
+        .x(1)
+        .label("origin".to_owned())
+        .clear_x()
+        .x(0)
+        .y(0)
+        .clear_label()
+        .build();
+    assert_eq!((point.x, point.y, point.label), (0, 0, None));
+
+    let mut builder = Private::builder();
+    builder._id(1).tag("a".to_owned())._tags(["b".to_owned()]);
+    assert_eq!(builder.get__id(), Some(&1));
+    assert!(builder.is__tags_set());
+    builder.set__tags(["c".to_owned()]).clear__id();
+    assert!(builder.build().is_err());
+    let private = builder._id(2).build().unwrap();
+    assert_eq!(private._tags, ["c"]);
+
+    let secret = Secret::builder()._key(1).clear__key()._key(2).build();
+    assert_eq!(secret._key, 2);
+}
@@ -0,0 +1,177 @@
+// With the `cli` feature of derive_builder, #[builder(cli)] generates
+// `from_args()`, which fills the builder from command line flags, so a binary
+// does not need a second struct describing its flags.
+//
+// Every field is a `--kebab-case` flag, whose value is the next argument or
+// follows `=`, parsed with FromStr. Required fields are required flags,
+// fields with a default or of type Option are optional flags, and `each`
+// fields are repeatable flags named after the `each` setter, a map entry
+// being given as KEY=VALUE. `bool` fields are switches. Leading underscores
+// are left out of the flag, and the unnamed fields of a tuple struct are
+// `--0`, `--1` and so on, unless they are given a name.
+//
+// `-h` and `--help` return the Help error, carrying the help text made from
+// the doc comments of the struct and its fields, which is also available as
+// `cli_help()`. Nothing outside of std is needed at runtime.
+//
+// The generated items are documented, for crates that deny `missing_docs`.
+//
+// This test only runs with `cargo test --features cli`.
+
+//! A binary taking its flags from a derived builder.
+
+#![deny(missing_docs)]
+
+use derive_builder::Builder;
+use std::collections::BTreeMap;
+
+/// Runs a command.
+#[derive(Builder, Debug)]
+#[builder(cli, derive(Debug))]
+pub struct Command {
+    /// The program to run.
+    executable: String,
+    /// An argument of the program.
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    /// An environment variable.
+    #[builder(each = "env")]
+    env: BTreeMap<String, String>,
+    /// The directory to run in.
+    current_dir: Option<String>,
+    /// Seconds before giving up.
+    #[builder(default = "30")]
+    timeout: u64,
+    /// Print what is run.
+    verbose: bool,
+}
+
+/// Resizes an image.
+#[derive(Builder, Debug)]
+#[builder(cli, derive(Debug))]
+pub struct Resize(
+    u32,
+    #[builder(name = "height")] u32,
+    #[builder(default)] bool,
+);
+
+/// Copies files.
+#[derive(Builder, Debug)]
+#[builder(cli, derive(Debug))]
+pub struct Copy {
+    /// The file to copy.
+    _source: String,
+    /// Only print what would be copied.
+    _dry_run: bool,
+}
+
+fn main() {
+    let command = CommandBuilder::from_args([
+        "--executable",
+        "cargo",
+        "--arg",
+        "build",
+        "--arg=--release",
+        "--env",
+        "RUST_LOG=debug",
+        "--verbose",
+    ])
+    .unwrap()
+    .build()
+    .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["build", "--release"]);
+    assert_eq!(command.env["RUST_LOG"], "debug");
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.timeout, 30);
+    assert!(command.verbose);
+
+    let command = CommandBuilder::from_args(vec![
+        "--executable=ls".to_owned(),
+        "--current-dir".to_owned(),
+        "/tmp".to_owned(),
+        "--timeout".to_owned(),
+        "5".to_owned(),
+    ])
+    .unwrap()
+    .build()
+    .unwrap();
+    assert_eq!(command.current_dir.as_deref(), Some("/tmp"));
+    assert_eq!(command.timeout, 5);
+    assert!(!command.verbose);
+
+    let err = CommandBuilder::from_args(["--arg", "build"]).unwrap_err();
+    assert_eq!(err, CommandBuilderCliError::MissingFlag("--executable"));
+    assert_eq!(err.to_string(), "missing required flag `--executable`");
+
+    let err = CommandBuilder::from_args(["--executable", "ls", "--timeout", "soon"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value `soon` for `--timeout`: invalid digit found in string"
+    );
+
+    let err = CommandBuilder::from_args(["--executable", "ls", "--env", "CI"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value `CI` for `--env`: expected KEY=VALUE"
+    );
+
+    let err = CommandBuilder::from_args(["--executable"]).unwrap_err();
+    assert_eq!(err, CommandBuilderCliError::MissingValue("--executable"));
+
+    let err = CommandBuilder::from_args(["--executable", "ls", "--force"]).unwrap_err();
+    assert_eq!(
+        err,
+        CommandBuilderCliError::UnknownArgument("--force".to_owned())
+    );
+
+    let help = "\
+Runs a command.
+
+Options:
+  --executable <EXECUTABLE>    The program to run. (required)
+  --arg <ARG>...               An argument of the program.
+  --env <KEY=VALUE>...         An environment variable.
+  --current-dir <CURRENT_DIR>  The directory to run in.
+  --timeout <TIMEOUT>          Seconds before giving up.
+  --verbose                    Print what is run.
+  -h, --help                   Print this help";
+    assert_eq!(CommandBuilder::cli_help(), help);
+    let err = CommandBuilder::from_args(["--arg", "x", "--help"]).unwrap_err();
+    assert_eq!(err, CommandBuilderCliError::Help(help));
+    assert_eq!(err.to_string(), help);
+
+    let resize = ResizeBuilder::from_args(["--0", "640", "--height", "480", "--2"])
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!((resize.0, resize.1, resize.2), (640, 480, true));
+    assert_eq!(
+        ResizeBuilder::cli_help(),
+        "\
+Resizes an image.
+
+Options:
+  --0 <VALUE>        (required)
+  --height <HEIGHT>  (required)
+  --2
+  -h, --help         Print this help"
+    );
+
+    let copy = CopyBuilder::from_args(["--source", "a.txt", "--dry-run"])
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(copy._source, "a.txt");
+    assert!(copy._dry_run);
+    assert_eq!(
+        CopyBuilder::cli_help(),
+        "\
+Copies files.
+
+Options:
+  --source <SOURCE>  The file to copy. (required)
+  --dry-run          Only print what would be copied.
+  -h, --help         Print this help"
+    );
+}
@@ -0,0 +1,119 @@
+// Services are commonly configured through the environment.
+// #[builder(env_prefix = "APP_")] generates `apply_env()`, which sets every
+// field that is still unset from the environment variable named after it:
+// the prefix followed by the field's name in upper case, such as
+// `APP_CURRENT_DIR`. Values are parsed with FromStr, and a value that does not
+// parse is reported with the name of its variable. A setter renamed with
+// #[builder(name = "...")] does not change the variable, and the fields of a
+// tuple struct are read from their index, such as `APP_0`.
+//
+// The variable of an `each` field holds a list, split on `,` or on the
+// separator given by #[builder(env_separator = "...")], with map entries
+// written as KEY=VALUE.
+//
+// `apply_env_from()` takes the variables from any iterator of name and value
+// pairs instead, which is also what keeps this test independent of the
+// environment it runs in.
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug)]
+#[builder(env_prefix = "APP_", derive(Debug))]
+pub struct Server {
+    host: String,
+    port: u16,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    #[builder(each = "label")]
+    labels: HashMap<String, u8>,
+    log_file: Option<String>,
+    #[builder(default)]
+    debug: bool,
+    #[builder(name = "timeout_secs", default = "30")]
+    timeout: u64,
+}
+
+#[derive(Builder, Debug)]
+#[builder(env_prefix = "SIZE_")]
+pub struct Size(u32, #[builder(name = "height")] u32);
+
+#[derive(Builder, Debug)]
+#[builder(env_prefix = "TOOL_", env_separator = ":")]
+pub struct Tool {
+    #[builder(each = "dir")]
+    path: Vec<String>,
+}
+
+fn main() {
+    let vars = [
+        ("APP_HOST", "0.0.0.0"),
+        ("APP_PORT", "8080"),
+        ("APP_TAGS", "web,public"),
+        ("APP_LABELS", "tier=1,zone=3"),
+        ("APP_DEBUG", "true"),
+        ("APP_TIMEOUT", "5"),
+        ("APP_TIMEOUT_SECS", "7"),
+        ("OTHER_PORT", "1"),
+    ];
+    let mut builder = Server::builder();
+    builder.host("localhost".to_owned());
+    builder.apply_env_from(vars).unwrap();
+    let server = builder.build().unwrap();
+    // fields set before are kept
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.tags, ["web", "public"]);
+    assert_eq!(server.labels["zone"], 3);
+    assert_eq!(server.log_file, None);
+    assert!(server.debug);
+    assert_eq!(server.timeout, 5);
+
+    let err = Server::builder()
+        .apply_env_from([("APP_PORT", "http")])
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ServerBuilderEnvError::InvalidValue {
+            var: "APP_PORT",
+            value: "http".to_owned(),
+            message: "invalid digit found in string".to_owned(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "invalid value `http` for `APP_PORT`: invalid digit found in string"
+    );
+
+    let err = Server::builder()
+        .apply_env_from([("APP_LABELS", "tier")])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value `tier` for `APP_LABELS`: expected KEY=VALUE"
+    );
+
+    let tool = Tool::builder()
+        .apply_env_from(vec![("TOOL_PATH".to_owned(), "/bin:/usr/bin".to_owned())])
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(tool.path, ["/bin", "/usr/bin"]);
+
+    let size = Size::builder()
+        .apply_env_from([("SIZE_0", "640"), ("SIZE_1", "480")])
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!((size.0, size.1), (640, 480));
+
+    std::env::set_var("APP_LOG_FILE", "/var/log/app.log");
+    let mut builder = Server::builder();
+    builder.apply_env().unwrap();
+    assert_eq!(builder.build().unwrap_err(), ServerBuilderError::UninitializedField("host"));
+    builder.host("localhost".to_owned()).port(80);
+    assert_eq!(
+        builder.build().unwrap().log_file.as_deref(),
+        Some("/var/log/app.log")
+    );
+}
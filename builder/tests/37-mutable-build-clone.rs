@@ -0,0 +1,36 @@
+// A failed build() leaves a mutable builder as it was, so the values that may
+// still fail once set are built from clones, and their types must be Clone:
+// the builder of a sub_builder field, and the stored value of a finalize_with
+// field. The missing Clone is reported at the field rather than at the
+// derive; an owned builder has no such requirement.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Server {
+    port: u16,
+}
+
+#[derive(Builder)]
+pub struct Config {
+    #[builder(sub_builder)]
+    server: Server,
+}
+
+pub struct Tls {
+    pub cert: String,
+}
+
+pub struct Connection;
+
+fn connect(_tls: Tls) -> Connection {
+    Connection
+}
+
+#[derive(Builder)]
+pub struct Client {
+    #[builder(finalize_with(path = "connect", ty = "Tls"))]
+    connection: Connection,
+}
+
+fn main() {}
@@ -10,4 +10,34 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-generics.rs");
+    t.pass("tests/11-typestate.rs");
+    t.compile_fail("tests/12-typestate-missing-field.rs");
+    t.pass("tests/13-error-type.rs");
+    t.pass("tests/14-default-values.rs");
+    t.pass("tests/15-builder-pattern.rs");
+    t.pass("tests/16-setter-conversions.rs");
+    t.pass("tests/17-validation.rs");
+    t.pass("tests/18-enum-variants.rs");
+    t.pass("tests/19-tuple-structs.rs");
+    t.pass("tests/20-each-collections.rs");
+    t.pass("tests/21-type-detection.rs");
+    t.compile_fail("tests/22-attribute-diagnostics.rs");
+    t.pass("tests/23-builder-naming.rs");
+    t.pass("tests/24-skip-fields.rs");
+    #[cfg(feature = "serde")]
+    t.pass("tests/25-serde.rs");
+    t.pass("tests/26-from-value.rs");
+    t.pass("tests/27-const-builder.rs");
+    t.compile_fail("tests/28-const-unsupported.rs");
+    t.pass("tests/29-no-std.rs");
+    t.pass("tests/30-docs.rs");
+    t.pass("tests/31-async-build.rs");
+    t.pass("tests/32-sub-builder.rs");
+    t.pass("tests/33-getters.rs");
+    t.pass("tests/34-clear-and-bulk-setters.rs");
+    #[cfg(feature = "cli")]
+    t.pass("tests/35-cli.rs");
+    t.pass("tests/36-env.rs");
+    t.compile_fail("tests/37-mutable-build-clone.rs");
 }